@@ -0,0 +1,217 @@
+//! Solving many deals in one go, to see how the solver does across lots of them.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::time::Duration;
+
+use ::board::{Board, RuleSet, Seed};
+use ::solver::{self, SolveOutcome, SolverConfig};
+use ::util::as_seconds;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DealOutcome {
+    Solved,
+    Unsolvable,
+    GaveUp,
+}
+
+impl fmt::Display for DealOutcome {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        formatter.pad(match self {
+            DealOutcome::Solved => "solved",
+            DealOutcome::Unsolvable => "unsolvable",
+            DealOutcome::GaveUp => "gave up",
+        })
+    }
+}
+
+/// How solving one deal went.
+pub struct DealResult {
+    pub seed: String,
+    pub outcome: DealOutcome,
+    /// Length of the solution, if one was found.
+    pub moves: Option<usize>,
+    pub nodes_expanded: u64,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for DealResult {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(formatter, "{}  {:<10}", self.seed, self.outcome)?;
+        match self.moves {
+            Some(moves) => write!(formatter, " {:>3} moves", moves)?,
+            None => write!(formatter, "          ")?,
+        }
+        write!(formatter, " {:>9} nodes {:>8.3}s", self.nodes_expanded, as_seconds(self.elapsed))
+    }
+}
+
+/// Deal the board for `seed` by `rules`, and solve it within the limits of `config`.
+pub fn solve_deal(seed: &Seed, rules: &RuleSet, config: &SolverConfig) -> DealResult {
+    let board = Board::deal_with_rules(seed, rules).do_automoves_with(config.automoves);
+    let report = solver::solve(&board, config).expect("dealt boards are always valid");
+    let (outcome, moves) = match report.outcome {
+        SolveOutcome::Solved(solution) => (DealOutcome::Solved, Some(solution.moves.len())),
+        SolveOutcome::ProvenUnsolvable => (DealOutcome::Unsolvable, None),
+        SolveOutcome::BudgetExhausted{..} => (DealOutcome::GaveUp, None),
+    };
+    DealResult{
+        seed: seed.to_string(),
+        outcome,
+        moves,
+        nodes_expanded: report.stats.nodes_expanded,
+        elapsed: report.stats.elapsed,
+    }
+}
+
+/// Totals and averages over a batch of `DealResult`s.
+pub struct Summary {
+    pub deals: usize,
+    pub solved: usize,
+    pub unsolvable: usize,
+    pub gave_up: usize,
+    /// Solution lengths, over the deals which were solved.
+    pub mean_moves: Option<f64>,
+    pub median_moves: Option<f64>,
+    pub max_moves: Option<usize>,
+    pub nodes_expanded: u64,
+    /// Wall time for the whole batch.
+    pub elapsed: Duration,
+}
+
+impl Summary {
+    pub fn new(results: &[DealResult], elapsed: Duration) -> Summary {
+        let count = |outcome| results.iter().filter(|result| result.outcome == outcome).count();
+        let mut moves: Vec<usize> = results.iter().filter_map(|result| result.moves).collect();
+        moves.sort();
+        let median_moves = if moves.is_empty() {None}
+            else if moves.len() % 2 == 1 {Some(moves[moves.len() / 2] as f64)}
+            else {Some((moves[moves.len() / 2 - 1] + moves[moves.len() / 2]) as f64 / 2.0)};
+        Summary{
+            deals: results.len(),
+            solved: count(DealOutcome::Solved),
+            unsolvable: count(DealOutcome::Unsolvable),
+            gave_up: count(DealOutcome::GaveUp),
+            mean_moves: if moves.is_empty() {None}
+                else {Some(moves.iter().sum::<usize>() as f64 / moves.len() as f64)},
+            median_moves,
+            max_moves: moves.last().cloned(),
+            nodes_expanded: results.iter().map(|result| result.nodes_expanded).sum(),
+            elapsed,
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rate = if self.deals == 0 {0.0} else {self.solved as f64 * 100.0 / self.deals as f64};
+        writeln!(f, "deals:          {}", self.deals)?;
+        writeln!(f, "solved:         {} ({:.1}%)", self.solved, rate)?;
+        writeln!(f, "unsolvable:     {}", self.unsolvable)?;
+        writeln!(f, "gave up:        {}", self.gave_up)?;
+        match (self.mean_moves, self.median_moves, self.max_moves) {
+            (Some(mean), Some(median), Some(max)) =>
+                writeln!(f, "moves:          mean {:.1}, median {}, max {}", mean, median, max)?,
+            _ => writeln!(f, "moves:          -")?,
+        }
+        writeln!(f, "nodes expanded: {}", self.nodes_expanded)?;
+        write!(f, "wall time:      {:.3}s", as_seconds(self.elapsed))
+    }
+}
+
+/// Write one line per result, with a header.
+///
+/// Seeds are Z85, which has no commas or quotes, so nothing needs escaping.
+pub fn write_csv<W: Write>(results: &[DealResult], mut out: W) -> io::Result<()> {
+    writeln!(out, "seed,outcome,moves,nodes_expanded,seconds")?;
+    for result in results {
+        writeln!(
+            out, "{},{},{},{},{:.3}",
+            result.seed,
+            result.outcome,
+            result.moves.map_or(String::new(), |moves| moves.to_string()),
+            result.nodes_expanded,
+            as_seconds(result.elapsed),
+        )?;
+    }
+    Ok(())
+}
+
+/// Write the results as a JSON array of objects, one per line.
+///
+/// Seeds are Z85, which has no quotes or backslashes, so nothing needs escaping.
+pub fn write_json<W: Write>(results: &[DealResult], mut out: W) -> io::Result<()> {
+    writeln!(out, "[")?;
+    for (i, result) in results.iter().enumerate() {
+        writeln!(
+            out,
+            "  {{\"seed\": \"{}\", \"outcome\": \"{}\", \"moves\": {}, \"nodes_expanded\": {}, \"seconds\": {:.3}}}{}",
+            result.seed,
+            result.outcome,
+            result.moves.map_or("null".to_string(), |moves| moves.to_string()),
+            result.nodes_expanded,
+            as_seconds(result.elapsed),
+            if i + 1 < results.len() {","} else {""},
+        )?;
+    }
+    writeln!(out, "]")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(outcome: DealOutcome, moves: Option<usize>) -> DealResult {
+        DealResult{
+            seed: "8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&".to_string(),
+            outcome,
+            moves,
+            nodes_expanded: 100,
+            elapsed: Duration::from_millis(1500),
+        }
+    }
+
+    #[test]
+    fn summary() {
+        let results = vec![
+            result(DealOutcome::Solved, Some(20)),
+            result(DealOutcome::Solved, Some(25)),
+            result(DealOutcome::GaveUp, None),
+            result(DealOutcome::Solved, Some(21)),
+            result(DealOutcome::Solved, Some(30)),
+        ];
+        let summary = Summary::new(&results, Duration::from_secs(8));
+        assert_eq!((summary.deals, summary.solved, summary.unsolvable, summary.gave_up), (5, 4, 0, 1));
+        assert_eq!(summary.mean_moves, Some(24.0));
+        assert_eq!(summary.median_moves, Some(23.0));
+        assert_eq!(summary.max_moves, Some(30));
+        assert_eq!(summary.nodes_expanded, 500);
+
+        let summary = Summary::new(&[], Duration::from_secs(0));
+        assert_eq!(summary.mean_moves, None);
+        assert_eq!(summary.median_moves, None);
+    }
+
+    #[test]
+    fn write_results() {
+        let results = vec![result(DealOutcome::Solved, Some(20)), result(DealOutcome::GaveUp, None)];
+
+        let mut csv = Vec::new();
+        write_csv(&results, &mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), indoc!("
+            seed,outcome,moves,nodes_expanded,seconds
+            8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&,solved,20,100,1.500
+            8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&,gave up,,100,1.500
+        "));
+
+        let mut json = Vec::new();
+        write_json(&results, &mut json).unwrap();
+        assert_eq!(String::from_utf8(json).unwrap(), indoc!(r#"
+            [
+              {"seed": "8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&", "outcome": "solved", "moves": 20, "nodes_expanded": 100, "seconds": 1.500},
+              {"seed": "8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&", "outcome": "gave up", "moves": null, "nodes_expanded": 100, "seconds": 1.500}
+            ]
+        "#));
+    }
+}
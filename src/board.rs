@@ -1,28 +1,176 @@
+//! Boards, the cards and cells they're made of, and the moves made on them.
+
 extern crate itertools;
 extern crate rand;
 extern crate zero85;
 use std::fmt;
 use std::hash::{Hash, Hasher};
-use std::rc::Rc;
+use std::sync::Arc;
 
 use self::itertools::sorted;
+use ::zobrist;
 use self::rand::{thread_rng, Rng, SeedableRng, StdRng};
-use self::zero85::{FromZ85, ToZ85};
+use self::zero85::{FromZ85, FromZ85Error, ToZ85};
 
+/// The suit of a number card or dragon.
 #[derive(Copy, Clone)]
 #[derive(Debug)]
 #[derive(Eq, PartialEq, Hash, PartialOrd, Ord)]
 pub enum Suit {
+    /// Shown as black, and the first of every rule set's suits.
     Black,
+    /// Shown as green.
     Green,
+    /// Shown as red.
     Red,
+    /// Only dealt by rule sets with a fourth suit.
+    Yellow,
+}
+
+/// Every suit, in the order rule sets with fewer suits leave them out from the end.
+const SUITS: [Suit; 4] = [Suit::Black, Suit::Green, Suit::Red, Suit::Yellow];
+
+impl fmt::Display for Suit {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(formatter, "{}", match self {
+            Suit::Black => "black",
+            Suit::Green => "green",
+            Suit::Red => "red",
+            Suit::Yellow => "yellow",
+        })
+    }
+}
+
+/// The shape of a game: how many of each kind of cell there are, and which cards are dealt.
+///
+/// The default is the real game's.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RuleSet {
+    /// How many free cells there are, each holding a single card or a grouped stack of dragons.
+    pub free_cells: usize,
+    /// How many game cells the cards are dealt into.
+    pub columns: usize,
+    /// How many suits are dealt, each with its own goal cell. At most four.
+    pub suits: usize,
+    /// Number cards of each suit are ranked from 1 up to this. At most 9.
+    pub ranks: u8,
+    /// How many dragons of each suit are dealt, all of which must be exposed to group them.
+    pub dragons: usize,
+    /// Whether the joker is dealt, along with a cell of its own to go to.
+    pub joker: bool,
+}
+
+/// Why `RuleSet::from_string` refused a description of some rules.
+#[derive(Debug, Eq, PartialEq)]
+pub enum RuleSetError {
+    /// A rule that isn't written `name=value`.
+    Malformed(String),
+    /// A rule with a name other than those `RuleSet` has fields for.
+    UnknownRule(String),
+    /// A value that doesn't parse, or is outside the range the rule allows.
+    InvalidValue{
+        /// The name of the rule.
+        rule: String,
+        /// The value it was given.
+        value: String,
+    },
+}
+
+impl fmt::Display for RuleSetError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            RuleSetError::Malformed(rule) =>
+                write!(formatter, "'{}' should be written as name=value", rule),
+            RuleSetError::UnknownRule(rule) => write!(
+                formatter,
+                "unknown rule '{}' (choose from free, columns, suits, ranks, dragons, joker)",
+                rule,
+            ),
+            RuleSetError::InvalidValue{rule, value} =>
+                write!(formatter, "'{}' is not a valid value for {}", value, rule),
+        }
+    }
 }
 
-#[derive(Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+impl RuleSet {
+    /// Parse rules written like `free=4,suits=4,joker=no`. Rules which aren't given are as in
+    /// the real game.
+    pub fn from_string(rules: &str) -> Result<RuleSet, RuleSetError> {
+        let mut rule_set = RuleSet::default();
+        for rule in rules.split(',').map(|rule| rule.trim()).filter(|rule| !rule.is_empty()) {
+            let (name, value) = match rule.find('=') {
+                Some(equals) => (rule[..equals].trim(), rule[equals + 1..].trim()),
+                None => return Err(RuleSetError::Malformed(rule.to_string())),
+            };
+            let invalid = || RuleSetError::InvalidValue{rule: name.to_string(), value: value.to_string()};
+            let number = |min: usize, max: usize| value.parse().ok()
+                .filter(|number| min <= *number && *number <= max)
+                .ok_or_else(invalid);
+            match name {
+                "free" => rule_set.free_cells = number(0, 9)?,
+                "columns" => rule_set.columns = number(1, 9)?,
+                "suits" => rule_set.suits = number(1, SUITS.len())?,
+                "ranks" => rule_set.ranks = number(1, 9)? as u8,
+                "dragons" => rule_set.dragons = number(0, 9)?,
+                "joker" => rule_set.joker = match value {
+                    "yes" => true,
+                    "no" => false,
+                    _ => return Err(invalid()),
+                },
+                _ => return Err(RuleSetError::UnknownRule(name.to_string())),
+            }
+        }
+        Ok(rule_set)
+    }
+
+    /// The suits dealt under these rules.
+    pub fn suits_in_play(&self) -> &'static [Suit] {
+        &SUITS[..self.suits]
+    }
+
+    /// Whether `card` could be dealt under these rules, going by its suit and rank alone.
+    pub fn deals(&self, card: &Card) -> bool {
+        match *card {
+            Card::NumberCard{suit, rank} => self.suits_in_play().contains(&suit) && rank <= self.ranks,
+            Card::DragonCard{suit} => self.suits_in_play().contains(&suit),
+            _ => true,
+        }
+    }
+}
+
+impl Default for RuleSet {
+    fn default() -> RuleSet {
+        RuleSet{free_cells: 3, columns: 8, suits: 3, ranks: 9, dragons: 4, joker: true}
+    }
+}
+
+impl fmt::Display for RuleSet {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            formatter, "free={},columns={},suits={},ranks={},dragons={},joker={}",
+            self.free_cells, self.columns, self.suits, self.ranks, self.dragons,
+            if self.joker {"yes"} else {"no"},
+        )
+    }
+}
+
+/// A single card, or what's left of the dragons once they're grouped.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
 pub enum Card {
+    /// The joker, which can only go to the joker cell.
     JokerCard,
-    DragonCard{suit: Suit},
-    NumberCard{suit: Suit, rank: u8},
+    /// One of the dragons of a suit, which can't be built on and are grouped once all exposed.
+    DragonCard{
+        /// The dragon's suit.
+        suit: Suit,
+    },
+    /// A card built on cards of other suits ranked one higher, and moved to the goal cells in order.
+    NumberCard{
+        /// The card's suit.
+        suit: Suit,
+        /// The card's rank, from 1 up to the rules' `ranks`.
+        rank: u8,
+    },
     /// Dummy "card" representing an immovable stack of dragons in a free cell.
     DragonStack,
 }
@@ -40,15 +188,43 @@ impl Card {
     }
 }
 
+impl fmt::Display for Card {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            Card::JokerCard => write!(formatter, "joker"),
+            Card::DragonCard{suit} => write!(formatter, "{} dragon", suit),
+            Card::NumberCard{suit, rank} => write!(formatter, "{} {}", suit, rank),
+            Card::DragonStack => write!(formatter, "dragon stack"),
+        }
+    }
+}
+
+/// A place on the board cards can be, and the cards in it.
 #[derive(Eq, PartialEq, Hash, PartialOrd, Ord)]
 pub enum CardCell {
-    JokerCell{has_joker: bool},
-    FreeCell{card: Option<Rc<Card>>},
-    GameCell{card_stack: Vec<Rc<Card>>},
-    GoalCell{top_card: Option<Rc<Card>>},
+    /// Where the joker goes once it's exposed.
+    JokerCell{
+        /// Whether the joker has gone there yet.
+        has_joker: bool,
+    },
+    /// A cell holding a single card, or the dragons of a suit once they're grouped.
+    FreeCell{
+        /// What the cell holds, if anything.
+        card: Option<Arc<Card>>,
+    },
+    /// One of the columns the cards are dealt into.
+    GameCell{
+        /// The cards in the column, from the bottom up.
+        card_stack: Vec<Arc<Card>>,
+    },
+    /// Where the number cards of one suit are built up in order, to win.
+    GoalCell{
+        /// The highest ranked card built up so far, which the cards below it are under.
+        top_card: Option<Arc<Card>>,
+    },
 }
 impl CardCell {
-    fn accept(&self, card: &Rc<Card>) -> Option<Self> {
+    fn accept(&self, card: &Arc<Card>) -> Option<Self> {
         match (self, &**card) {
             (_, &Card::DragonStack) => None,
 
@@ -80,7 +256,7 @@ impl CardCell {
     ///
     /// Assumes `cards` is properly formed, ie not empty and all NumberCards, in descending order,
     /// with no matching Suit across consecutive cards.
-    fn accept_stack(&self, cards: &[Rc<Card>]) -> Option<Self> {
+    fn accept_stack(&self, cards: &[Arc<Card>]) -> Option<Self> {
         if let CardCell::GameCell{card_stack} = self {
             if let Some(rc_card) = card_stack.last() {
                 let card = cards.first().expect("cards must be nonempty");
@@ -95,7 +271,24 @@ impl CardCell {
         else {panic!("Only GameCells may accept stacks.");}
     }
 
-    pub fn top(&self) -> Option<Rc<Card>> {
+    /// Number of cards in this cell. A JokerCell holding the joker counts as one, as does a
+    /// GoalCell (no matter how many cards are stacked beneath its top card).
+    pub fn len(&self) -> usize {
+        match self {
+            CardCell::JokerCell{has_joker} => *has_joker as usize,
+            CardCell::FreeCell{card} => card.is_some() as usize,
+            CardCell::GameCell{card_stack} => card_stack.len(),
+            CardCell::GoalCell{top_card} => top_card.is_some() as usize,
+        }
+    }
+
+    /// Whether this cell holds no cards, as counted by `len`.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The exposed card in this cell, if there is one.
+    pub fn top(&self) -> Option<Arc<Card>> {
         match self {
             CardCell::GoalCell{top_card: Some(ref card)} => Some(card.clone()),
             CardCell::FreeCell{card: Some(ref card)} => Some(card.clone()),
@@ -125,12 +318,12 @@ impl CardCell {
         }
     }
 
-    fn iter_stack(&self) -> Vec<Rc<Card>> {
+    fn iter_stack(&self) -> Vec<Arc<Card>> {
         match &self {
             CardCell::GameCell{card_stack} => {
-                let mut result: Vec<Rc<Card>> = Vec::new();
+                let mut result: Vec<Arc<Card>> = Vec::new();
                 let mut iter = card_stack.iter().rev();
-                let mut last_card: Rc<Card>;
+                let mut last_card: Arc<Card>;
                 if let Some(rc_card) = iter.next() {
                     last_card = rc_card.clone();
                     result.push(last_card.clone());
@@ -156,92 +349,312 @@ impl CardCell {
 }
 
 /// Enum to refer to the different card cells on a board.
-#[derive(Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum CardCellIndex {
+    /// A free cell, counting from 0 on the left.
     FreeCellIndex(usize),
+    /// A goal cell, counting from 0 on the left.
     GoalCellIndex(usize),
+    /// A column, counting from 0 on the left.
     GameCellIndex(usize),
 }
 
+impl fmt::Display for CardCellIndex {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            CardCellIndex::FreeCellIndex(n) => write!(formatter, "free cell {}", n + 1),
+            CardCellIndex::GoalCellIndex(n) => write!(formatter, "goal cell {}", n + 1),
+            CardCellIndex::GameCellIndex(n) => write!(formatter, "column {}", n + 1),
+        }
+    }
+}
+
+/// A single move made by the player. Automoves are not considered moves.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Move {
+    /// Move the top `height` cards of `source` onto `dest`.
+    MoveStack{
+        /// The cell the cards are taken from.
+        source: CardCellIndex,
+        /// The cell the cards are put on.
+        dest: CardCellIndex,
+        /// How many cards are moved.
+        height: u8,
+    },
+    /// Group the four exposed dragons of `suit` into a free cell.
+    GroupDragons{
+        /// The suit of the dragons grouped.
+        suit: Suit,
+    },
+}
+
+/// Why `Board::new` or `Board::with_rules` refused the cells it was given.
+#[derive(Debug, Eq, PartialEq)]
+pub enum BoardError {
+    /// The wrong number of free, goal or game cells were given.
+    WrongCellCount{
+        /// Which kind of cells, eg "free".
+        kind: &'static str,
+        /// How many of them the rules call for.
+        expected: usize,
+        /// How many of them were given.
+        found: usize,
+    },
+}
+
+impl fmt::Display for BoardError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            BoardError::WrongCellCount{kind, expected, found} =>
+                write!(formatter, "expected {} {} cells, found {}", expected, kind, found),
+        }
+    }
+}
+
+/// Which cards are moved to the goal cells without the player moving them, after every move.
+///
+/// The joker is always moved to its cell as soon as it's exposed, as in the game, since there's
+/// nowhere else worth moving it.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum AutoMovePolicy {
+    /// As Shenzhen I/O does: a number card is moved once every card of a lower rank, of any
+    /// suit, is in the goal cells. This is the default.
+    #[default]
+    GameAccurate,
+    /// Only 1s are moved.
+    Conservative,
+    /// No number cards are moved.
+    None,
+}
+
+impl AutoMovePolicy {
+    /// The policy named `name`, as its `Display` writes it: `game`, `conservative` or `none`.
+    pub fn from_name(name: &str) -> Option<AutoMovePolicy> {
+        match name {
+            "game" => Some(AutoMovePolicy::GameAccurate),
+            "conservative" => Some(AutoMovePolicy::Conservative),
+            "none" => Some(AutoMovePolicy::None),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AutoMovePolicy {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(formatter, "{}", match self {
+            AutoMovePolicy::GameAccurate => "game",
+            AutoMovePolicy::Conservative => "conservative",
+            AutoMovePolicy::None => "none",
+        })
+    }
+}
+
+/// Why `Board::move_stack` didn't make a move.
 pub enum MoveStackError {
+    /// More than one number of cards could be moved, up to the given height of the movable stack.
     AmbiguousMove(u8),
+    /// The move isn't allowed, eg the source is empty or a card can't go on the destination.
     InvalidMove,
 }
 
+/// A reason a board could not have come from a real game, as reported by `Board::validate`.
+#[derive(Debug, Eq, PartialEq)]
+pub enum Violation {
+    /// A number card is nowhere on the board, not even accounted for by a goal cell.
+    MissingCard(Card),
+    /// A number card is in play more than once.
+    DuplicateCard(Card),
+    /// A number card is in play even though a goal cell of its suit has already passed its rank.
+    GoalCardInPlay(Card),
+    /// Two goal cells are building the same suit.
+    DuplicateGoalSuit(Suit),
+    /// More dragons of a suit are in play than the rules deal.
+    TooManyDragons{
+        /// The dragons' suit.
+        suit: Suit,
+        /// How many of them are in play.
+        count: usize,
+    },
+    /// Some, but not all, dragons of a suit are in play.
+    MissingDragons{
+        /// The dragons' suit.
+        suit: Suit,
+        /// How many of them are in play.
+        count: usize,
+    },
+    /// The number of DragonStacks doesn't match the number of suits with no dragons in play.
+    DragonStackMismatch{
+        /// How many DragonStacks there are.
+        stacks: usize,
+        /// How many suits have no dragons in play.
+        grouped_suits: usize,
+    },
+    /// The joker is neither in play nor in the joker cell.
+    MissingJoker,
+    /// The joker is on the board more than once.
+    DuplicateJoker,
+    /// The joker is on the board, but the rules don't deal one.
+    UnexpectedJoker,
+    /// A number card ranked higher than the rules deal, or of a suit they don't deal, is in play.
+    UnexpectedCard(Card),
+    /// A goal cell holds a number card ranked higher than the rules deal, or of a suit they don't
+    /// deal.
+    UnexpectedGoalCard(Card),
+    /// A card is somewhere it can't be, eg a DragonStack outside the free cells or a dragon in a
+    /// goal cell.
+    MisplacedCard{
+        /// The cell the card is in.
+        index: CardCellIndex,
+        /// The card.
+        card: Card,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            Violation::MissingCard(card) => write!(formatter, "the {} is missing", card),
+            Violation::DuplicateCard(card) =>
+                write!(formatter, "the {} appears more than once", card),
+            Violation::GoalCardInPlay(card) =>
+                write!(formatter, "the {} is in play, but its goal cell is already past it", card),
+            Violation::DuplicateGoalSuit(suit) =>
+                write!(formatter, "more than one goal cell holds {} cards", suit),
+            Violation::TooManyDragons{suit, count} =>
+                write!(formatter, "there are {} {} dragons, more than are dealt", count, suit),
+            Violation::MissingDragons{suit, count} =>
+                write!(formatter, "there are {} {} dragons, but all of them must be in play", count, suit),
+            Violation::DragonStackMismatch{stacks, grouped_suits} => write!(
+                formatter,
+                "there are {} grouped dragon stacks, but {} suits of dragons have been grouped",
+                stacks, grouped_suits,
+            ),
+            Violation::MissingJoker => write!(formatter, "the joker is missing"),
+            Violation::DuplicateJoker => write!(formatter, "the joker appears more than once"),
+            Violation::UnexpectedJoker => write!(formatter, "there's a joker, but none is dealt"),
+            Violation::UnexpectedCard(card) => write!(formatter, "the {} is never dealt", card),
+            Violation::UnexpectedGoalCard(card) =>
+                write!(formatter, "a goal cell holds the {}, which is never dealt", card),
+            Violation::MisplacedCard{index, card} =>
+                write!(formatter, "a {} can't be in {}", card, index),
+        }
+    }
+}
+
+/// The state of a game: every card, and which cell it's in.
+///
+/// Boards are immutable; making a move returns a new board, which shares whichever cells the move
+/// didn't touch. Two boards are equal if they differ only in the order of their free cells or of
+/// their game cells, since neither order matters for winning.
 #[derive(Clone)]
 pub struct Board {
-    joker_cell: Rc<CardCell>,
-    free_cells: [Rc<CardCell>; 3],
-    goal_cells: [Rc<CardCell>; 3],
-    game_cells: [Rc<CardCell>; 8],
+    /// The rules this board is played by. Boards are only ever compared with boards played by
+    /// the same rules, so these are left out of comparisons.
+    rules: Arc<RuleSet>,
+    joker_cell: Arc<CardCell>,
+    free_cells: Vec<Arc<CardCell>>,
+    goal_cells: Vec<Arc<CardCell>>,
+    game_cells: Vec<Arc<CardCell>>,
+    /// The board's hash, as described in `zobrist`. Kept up to date whenever a cell is replaced.
+    zobrist: u64,
 }
 
 impl Board {
-    pub fn joker_cell(&self) -> &Rc<CardCell> {&self.joker_cell}
-    pub fn free_cells(&self) -> &[Rc<CardCell>; 3] {&self.free_cells}
-    pub fn goal_cells(&self) -> &[Rc<CardCell>; 3] {&self.goal_cells}
-    pub fn game_cells(&self) -> &[Rc<CardCell>; 8] {&self.game_cells}
+    /// The rules the board is played by.
+    pub fn rules(&self) -> &RuleSet {&self.rules}
+    /// The joker cell, which is there even when the rules don't deal a joker.
+    pub fn joker_cell(&self) -> &Arc<CardCell> {&self.joker_cell}
+    /// The free cells, left to right.
+    pub fn free_cells(&self) -> &[Arc<CardCell>] {&self.free_cells}
+    /// The goal cells, left to right.
+    pub fn goal_cells(&self) -> &[Arc<CardCell>] {&self.goal_cells}
+    /// The columns, left to right.
+    pub fn game_cells(&self) -> &[Arc<CardCell>] {&self.game_cells}
+    /// A hash of the board which doesn't depend on the order of its cells, and which is cheap to
+    /// get: it's updated as cards move rather than worked out when asked for.
+    pub fn zobrist_hash(&self) -> u64 {self.zobrist}
+
+    /// A board played by the real game's rules. See `with_rules`.
+    pub fn new(free_cells: Vec<Option<Card>>, joker_cell: bool, goal_cells: Vec<Option<Card>>, game_cells: Vec<Vec<Card>>) -> Result<Board, BoardError> {
+        Board::with_rules(&RuleSet::default(), free_cells, joker_cell, goal_cells, game_cells)
+    }
 
+    /// A board played by `rules`, from its free cells, whether the joker is in its cell, the top
+    /// card of each goal cell, and each game cell's cards from the bottom up. Fails if there are
+    /// more or fewer cells than `rules` calls for; see `validate` for whether the cards make sense.
     // pining for named arguments
-    pub fn new(free_cells: Vec<Option<Card>>, joker_cell: bool, goal_cells: Vec<Option<Card>>, game_cells: Vec<Vec<Card>>) -> Board {
-        let mut free_cells = free_cells.into_iter().map(|cell|
-            Rc::new(CardCell::FreeCell{card: cell.map(|card| Rc::new(card))})
-        );
-        let mut goal_cells = goal_cells.into_iter().map(|cell|
-            Rc::new(CardCell::GoalCell{top_card: cell.map(|card| Rc::new(card))})
-        );
-        let mut game_cells = game_cells.into_iter().map(|cell|
-            Rc::new(CardCell::GameCell{card_stack: cell.into_iter().map(|card| Rc::new(card)).collect()})
-        );
-
-        Board{
-            joker_cell: Rc::new(CardCell::JokerCell{has_joker: joker_cell}),
-            // TODO: I hate this, but I'm tired of fighting rust over it.
-            // Maybe revisit this someday:
-            // https://llogiq.github.io/2016/04/28/arraymap.html
-            free_cells: [
-                free_cells.next().unwrap(),
-                free_cells.next().unwrap(),
-                free_cells.next().unwrap(),
-            ],
-            goal_cells: [
-                goal_cells.next().unwrap(),
-                goal_cells.next().unwrap(),
-                goal_cells.next().unwrap(),
-            ],
-            game_cells: [
-                game_cells.next().unwrap(),
-                game_cells.next().unwrap(),
-                game_cells.next().unwrap(),
-                game_cells.next().unwrap(),
-                game_cells.next().unwrap(),
-                game_cells.next().unwrap(),
-                game_cells.next().unwrap(),
-                game_cells.next().unwrap(),
-            ],
+    pub fn with_rules(rules: &RuleSet, free_cells: Vec<Option<Card>>, joker_cell: bool, goal_cells: Vec<Option<Card>>, game_cells: Vec<Vec<Card>>) -> Result<Board, BoardError> {
+        let counts = [
+            ("free", rules.free_cells, free_cells.len()),
+            ("goal", rules.suits, goal_cells.len()),
+            ("game", rules.columns, game_cells.len()),
+        ];
+        for &(kind, expected, found) in counts.iter() {
+            if expected != found {
+                return Err(BoardError::WrongCellCount{kind, expected, found});
+            }
         }
+        let free_cells: Vec<_> = free_cells.into_iter().map(|cell|
+            Arc::new(CardCell::FreeCell{card: cell.map(|card| Arc::new(card))})
+        ).collect();
+        let goal_cells: Vec<_> = goal_cells.into_iter().map(|cell|
+            Arc::new(CardCell::GoalCell{top_card: cell.map(|card| Arc::new(card))})
+        ).collect();
+        let game_cells: Vec<_> = game_cells.into_iter().map(|cell|
+            Arc::new(CardCell::GameCell{card_stack: cell.into_iter().map(|card| Arc::new(card)).collect()})
+        ).collect();
+
+        let mut board = Board{
+            rules: Arc::new(rules.clone()),
+            joker_cell: Arc::new(CardCell::JokerCell{has_joker: joker_cell}),
+            free_cells,
+            goal_cells,
+            game_cells,
+            zobrist: 0,
+        };
+        board.zobrist = board.compute_zobrist();
+        Ok(board)
+    }
 
+    /// Work out the board's hash from scratch.
+    fn compute_zobrist(&self) -> u64 {
+        let cells = self.free_cells.iter().chain(self.goal_cells.iter()).chain(self.game_cells.iter());
+        cells.fold(zobrist::cell_key(&self.joker_cell), |sum, cell| sum.wrapping_add(zobrist::cell_key(cell)))
     }
 
+    /// Deal a random board by the real game's rules, along with the seed that deals it again.
     pub fn deal() -> (Board, Seed) {
         let seed = Seed::random();
         (Board::deal_seeded(&seed), seed)
     }
 
+    /// Deal the board for `seed` by the real game's rules.
     pub fn deal_seeded(seed: &Seed) -> Board {
-        let mut deck = create_deck();
+        Board::deal_with_rules(seed, &RuleSet::default())
+    }
+
+    /// Deal a board for the given rules. The same seed deals differently under different rules.
+    pub fn deal_with_rules(seed: &Seed, rules: &RuleSet) -> Board {
+        let mut deck = create_deck(rules);
         StdRng::from_seed(seed.key).shuffle(&mut deck);
 
-        Board::new(
-            vec![None, None, None], false, vec![None, None, None],
-            distribute(deck, 8),
-        )
+        Board::with_rules(
+            rules, vec![None; rules.free_cells], false, vec![None; rules.suits],
+            distribute(deck, rules.columns),
+        ).expect("dealt boards always have the right number of cells")
+    }
+
+    /// Replace `*cell` with `new_cell`, updating the board hash `zobrist` to match.
+    fn set_cell(cell: &mut Arc<CardCell>, new_cell: CardCell, zobrist: &mut u64) {
+        *zobrist = zobrist.wrapping_add(zobrist::cell_delta(cell, &new_cell));
+        *cell = Arc::new(new_cell);
     }
 
-    fn move_card(source: &mut Rc<CardCell>, dest: &mut Rc<CardCell>) -> bool {
+    fn move_card(source: &mut Arc<CardCell>, dest: &mut Arc<CardCell>, zobrist: &mut u64) -> bool {
         if let Some(new_cell) = dest.accept(&source.top().expect("me am play gods")) {
-            *dest = Rc::new(new_cell);
-            *source = Rc::new(source.pop());
+            Board::set_cell(dest, new_cell, zobrist);
+            let new_source = source.pop();
+            Board::set_cell(source, new_source, zobrist);
             return true;
         }
         false
@@ -276,10 +689,13 @@ impl Board {
     /// ways to accomplish the requested move, AmbiguousMove is returned (and `move_n_cards` should
     /// be called instead).
     pub fn move_stack(&self, source: &CardCellIndex, dest: &CardCellIndex) -> Result<Board, MoveStackError> {
-        // Can't move in-place.
+        // Can't move in-place, or out of a goal cell.
         if source == dest {
             return Err(MoveStackError::InvalidMove);
         }
+        if let &CardCellIndex::GoalCellIndex(_) = source {
+            return Err(MoveStackError::InvalidMove);
+        }
 
         // We might need to special-case moving between game cells, for stacks of num cards.
         if let (
@@ -328,14 +744,25 @@ impl Board {
 
     fn replace_cell(&mut self, index: &CardCellIndex, new_cell: CardCell) {
         // might be nice to check that the cell type is right
-        match index {
-            &CardCellIndex::FreeCellIndex(n) => self.free_cells[n] = Rc::new(new_cell),
-            &CardCellIndex::GoalCellIndex(n) => self.goal_cells[n] = Rc::new(new_cell),
-            &CardCellIndex::GameCellIndex(n) => self.game_cells[n] = Rc::new(new_cell),
+        let cell = match index {
+            &CardCellIndex::FreeCellIndex(n) => &mut self.free_cells[n],
+            &CardCellIndex::GoalCellIndex(n) => &mut self.goal_cells[n],
+            &CardCellIndex::GameCellIndex(n) => &mut self.game_cells[n],
+        };
+        Board::set_cell(cell, new_cell, &mut self.zobrist);
+    }
+
+    /// Whether the board has a cell at `index`, which depends on its rules.
+    pub fn has_cell(&self, index: &CardCellIndex) -> bool {
+        match *index {
+            CardCellIndex::FreeCellIndex(n) => n < self.free_cells.len(),
+            CardCellIndex::GoalCellIndex(n) => n < self.goal_cells.len(),
+            CardCellIndex::GameCellIndex(n) => n < self.game_cells.len(),
         }
     }
 
-    pub fn get_cell(&self, index: &CardCellIndex) -> &Rc<CardCell> {
+    /// The cell at `index`, which MUST be one the board has.
+    pub fn get_cell(&self, index: &CardCellIndex) -> &Arc<CardCell> {
         match index {
             &CardCellIndex::FreeCellIndex(n) => &self.free_cells[n],
             &CardCellIndex::GoalCellIndex(n) => &self.goal_cells[n],
@@ -343,6 +770,7 @@ impl Board {
         }
     }
 
+    /// Move the top `n` cards of `source` onto `dest`, or return None if that's illegal.
     pub fn move_n_cards(&self, source: &CardCellIndex, dest: &CardCellIndex, n: usize) -> Option<Board> {
         match (source, dest) {
             (&CardCellIndex::GameCellIndex(source_idx), &CardCellIndex::GameCellIndex(dest_idx)) =>
@@ -358,9 +786,11 @@ impl Board {
         }
 
         let mut board = self.clone();
-        board.game_cells[source] = Rc::new(board.game_cells[source].pop_n(n));
+        let new_source = board.game_cells[source].pop_n(n);
+        board.replace_cell(&CardCellIndex::GameCellIndex(source), new_source);
         let substack = &stack[stack.len() - n..];
-        board.game_cells[dest] = Rc::new(board.game_cells[dest].accept_stack(substack)?);
+        let new_dest = board.game_cells[dest].accept_stack(substack)?;
+        board.replace_cell(&CardCellIndex::GameCellIndex(dest), new_dest);
         Some(board)
     }
 
@@ -369,14 +799,19 @@ impl Board {
     ///
     /// Note that this leaves the board in an impossible state!
     fn remove_dragons(&mut self, suit: Suit) -> bool {
+        let dragons = self.rules.dragons;
+        if dragons == 0 {
+            return false
+        }
         let mut count = 0;
         for mut cell in self.game_cells.iter_mut().chain(self.free_cells.iter_mut()) {
             match cell.top() {
                 Some(rc_card) => match *rc_card {
                     Card::DragonCard{suit: dsuit} if dsuit == suit => {
-                        *cell = Rc::new(cell.pop());
+                        let new_cell = cell.pop();
+                        Board::set_cell(cell, new_cell, &mut self.zobrist);
                         count += 1;
-                        if count == 4 {
+                        if count == dragons {
                             return true
                         }
                     },
@@ -388,66 +823,201 @@ impl Board {
         false
     }
 
-    /// Stack exposed dragons of the given suit into an open free cell, and return the resulting
-    /// board.
+    /// Stack exposed dragons of the given suit into a free cell, and return the resulting board.
     ///
-    /// If not all dragons are exposed or no free cell is open, returns None instead.
+    /// As in the real game, the stack goes into the free cell already holding one of the dragons
+    /// if there is one, and otherwise into the leftmost open free cell. If not all dragons are
+    /// exposed, or none of them is in a free cell and no free cell is open, returns None instead.
     pub fn stack_dragons(&self, suit: Suit) -> Option<Board> {
-        // TODO: this function would love some tests.
+        let holding_dragon = self.free_cells.iter().position(|cell| match cell.top() {
+            Some(rc_card) => *rc_card == Card::DragonCard{suit},
+            None => false,
+        });
         let mut board = self.clone();
         if !board.remove_dragons(suit) {
             return None
         }
-        let mut found = false;
-        for mut cell in board.free_cells.iter_mut() {
-            if cell.top().is_none() {
-                *cell = Rc::new(CardCell::FreeCell{card: Some(Rc::new(Card::DragonStack))});
-                // Can't just return here, because we're already
-                // borrowing `board` to iterate it, I guess. 🤮
-                found = true;
-                break;
-            }
+        let dest = holding_dragon.or_else(|| self.free_cells.iter().position(|cell| cell.top().is_none()))?;
+        board.replace_cell(
+            &CardCellIndex::FreeCellIndex(dest),
+            CardCell::FreeCell{card: Some(Arc::new(Card::DragonStack))},
+        );
+        Some(board)
+    }
+
+    /// Make the given move and return the resulting board, or None if the move is illegal.
+    ///
+    /// Automoves are not performed; see `do_automoves`.
+    pub fn apply_move(&self, mv: &Move) -> Option<Board> {
+        match *mv {
+            Move::GroupDragons{suit} => self.stack_dragons(suit),
+            Move::MoveStack{ref source, ref dest, ..} if !self.has_cell(source) || !self.has_cell(dest) =>
+                None,
+            Move::MoveStack{ref source, ref dest, height} => match self.move_stack(source, dest) {
+                Ok(board) => {
+                    let moved = self.get_cell(source).len() - board.get_cell(source).len();
+                    if moved == height as usize {Some(board)} else {None}
+                },
+                Err(MoveStackError::AmbiguousMove(_)) =>
+                    self.move_n_cards(source, dest, height as usize),
+                Err(MoveStackError::InvalidMove) => None,
+            },
         }
-        if found {Some(board)}
-        else {None}
     }
 
-    pub fn is_solved(&self) -> bool {
-        for cell in self.game_cells.iter() {
-            if let Some(_) = cell.top() {
-                return false;
+    /// Check that this board could have come from a real game, returning every reason it
+    /// couldn't. An empty result means the board is valid.
+    ///
+    /// Boards from `deal` are always valid, as is any board reached from one by legal moves, but
+    /// `Board::new` accepts any cards at all.
+    pub fn validate(&self) -> Vec<Violation> {
+        let suits = self.rules.suits_in_play();
+        let mut violations = Vec::new();
+        // Every card in play, duplicates and all.
+        let mut in_play: Vec<Card> = Vec::new();
+
+        let mut jokers = if let CardCell::JokerCell{has_joker: true} = *self.joker_cell {1} else {0};
+        let mut stacks = 0;
+        let mut goal_ranks: Vec<(Suit, u8)> = Vec::new();
+
+        for cell in self.free_cells.iter() {
+            match cell.top().map(|card| *card) {
+                Some(Card::DragonStack) => stacks += 1,
+                Some(card) => in_play.push(card),
+                None => (),
+            }
+        }
+        for (i, cell) in self.goal_cells.iter().enumerate() {
+            match cell.top().map(|card| *card) {
+                Some(card @ Card::NumberCard{..}) if !self.rules.deals(&card) =>
+                    violations.push(Violation::UnexpectedGoalCard(card)),
+                Some(Card::NumberCard{suit, rank}) => {
+                    if goal_ranks.iter().any(|&(goal_suit, _)| goal_suit == suit) {
+                        violations.push(Violation::DuplicateGoalSuit(suit));
+                    }
+                    else {
+                        goal_ranks.push((suit, rank));
+                    }
+                },
+                Some(card) => violations.push(
+                    Violation::MisplacedCard{index: CardCellIndex::GoalCellIndex(i), card},
+                ),
+                None => (),
+            }
+        }
+        for (i, cell) in self.game_cells.iter().enumerate() {
+            if let CardCell::GameCell{ref card_stack} = **cell {
+                for card in card_stack.iter() {
+                    match **card {
+                        Card::DragonStack => violations.push(
+                            Violation::MisplacedCard{index: CardCellIndex::GameCellIndex(i), card: **card},
+                        ),
+                        card => in_play.push(card),
+                    }
+                }
+            }
+        }
+
+        for &suit in suits.iter() {
+            let goal_rank = goal_ranks.iter()
+                .find(|&&(goal_suit, _)| goal_suit == suit)
+                .map_or(0, |&(_, rank)| rank);
+            for rank in 1..=self.rules.ranks {
+                let card = Card::NumberCard{suit, rank};
+                let count = in_play.iter().filter(|&&other| other == card).count();
+                if rank <= goal_rank {
+                    if count > 0 {
+                        violations.push(Violation::GoalCardInPlay(card));
+                    }
+                }
+                else if count == 0 {
+                    violations.push(Violation::MissingCard(card));
+                }
+                else if count > 1 {
+                    violations.push(Violation::DuplicateCard(card));
+                }
             }
         }
-        true
+
+        for &card in in_play.iter() {
+            if !self.rules.deals(&card) && !violations.contains(&Violation::UnexpectedCard(card)) {
+                violations.push(Violation::UnexpectedCard(card));
+            }
+        }
+
+        let dragons = self.rules.dragons;
+        let mut grouped_suits = 0;
+        for &suit in suits.iter() {
+            let count = in_play.iter().filter(|&&card| card == Card::DragonCard{suit}).count();
+            match count {
+                count if count > dragons => violations.push(Violation::TooManyDragons{suit, count}),
+                // With no dragons dealt, there are none to group.
+                0 if dragons > 0 => grouped_suits += 1,
+                count if count == dragons => (),
+                count => violations.push(Violation::MissingDragons{suit, count}),
+            }
+        }
+        if stacks != grouped_suits {
+            violations.push(Violation::DragonStackMismatch{stacks, grouped_suits});
+        }
+
+        jokers += in_play.iter().filter(|&&card| card == Card::JokerCard).count();
+        match (jokers, self.rules.joker) {
+            (0, true) => violations.push(Violation::MissingJoker),
+            (0, false) | (1, true) => (),
+            (_, false) => violations.push(Violation::UnexpectedJoker),
+            (_, true) => violations.push(Violation::DuplicateJoker),
+        }
+
+        violations
     }
 
-    /// The maximum rank of number card that is safe to auto-move to the goal.
-    pub fn auto_safe_rank(&self) -> u8 {
-        self.goal_cells.iter().map(|cell| match cell.top() {
-            Some(rc) => match *rc {
-                Card::NumberCard{rank, ..} => rank,
-                _ => unreachable!(),  // no other card type should be in a goal cell
-            },
-            None => 0,
-        }).min().expect("goal cells is a sized array you goof.") + 2
+    /// Whether every number card is in the goal cells, every dragon is grouped and the joker is
+    /// in its cell.
+    pub fn is_solved(&self) -> bool {
+        self.game_cells.iter().all(|cell| cell.top().is_none()) &&
+        self.free_cells.iter().all(|cell| match cell.top() {
+            Some(rc_card) => *rc_card == Card::DragonStack,
+            None => true,
+        })
+    }
+
+    /// The maximum rank of number card that `policy` auto-moves to the goal.
+    pub fn auto_safe_rank(&self, policy: AutoMovePolicy) -> u8 {
+        match policy {
+            AutoMovePolicy::GameAccurate => self.goal_cells.iter().map(|cell| match cell.top() {
+                Some(rc) => match *rc {
+                    Card::NumberCard{rank, ..} => rank,
+                    _ => unreachable!(),  // no other card type should be in a goal cell
+                },
+                None => 0,
+            }).min().expect("goal cells is a sized array you goof.") + 1,
+            AutoMovePolicy::Conservative => 1,
+            AutoMovePolicy::None => 0,
+        }
     }
 
-    /// Perform moves which are always safe to do and return the resulting board.
+    /// Perform the moves the real game makes on its own, and return the resulting board.
     pub fn do_automoves(&self) -> Board {
+        self.do_automoves_with(AutoMovePolicy::GameAccurate)
+    }
+
+    /// Perform the moves `policy` makes on its own, and return the resulting board.
+    pub fn do_automoves_with(&self, policy: AutoMovePolicy) -> Board {
         let mut board = self.clone();
         let mut progress = true;
-        let mut safe_rank = self.auto_safe_rank();
+        let mut safe_rank = self.auto_safe_rank(policy);
 
         while progress {
             progress = false;
             for mut cell in board.game_cells.iter_mut().chain(board.free_cells.iter_mut()) {
                 progress = match cell.top() {
                     Some(rc_card) => match *rc_card {
-                        Card::JokerCard => Board::move_card(cell, &mut board.joker_cell),
+                        Card::JokerCard => Board::move_card(cell, &mut board.joker_cell, &mut board.zobrist),
                         Card::NumberCard{rank, ..} if rank <= safe_rank => {
                             let mut did = false;
                             for mut goal in board.goal_cells.iter_mut() {
-                                if Board::move_card(cell, goal) {
+                                if Board::move_card(cell, goal, &mut board.zobrist) {
                                     did = true;
                                     break
                                 }
@@ -459,7 +1029,7 @@ impl Board {
                     None => false,
                 } || progress;
             }
-            safe_rank = board.auto_safe_rank();
+            safe_rank = board.auto_safe_rank(policy);
         }
         board
     }
@@ -467,6 +1037,9 @@ impl Board {
 
 impl PartialEq for Board {
     fn eq(&self, rhs: &Board) -> bool {
+        // Boards with different hashes can't be equal, and most unequal boards have different
+        // hashes, so this saves sorting their cells.
+        self.zobrist == rhs.zobrist &&
         self.joker_cell == rhs.joker_cell &&
         sorted(self.game_cells.iter()) == sorted(rhs.game_cells.iter()) &&
         sorted(self.free_cells.iter()) == sorted(rhs.free_cells.iter()) &&
@@ -480,30 +1053,54 @@ impl Hash for Board {
     fn hash<H>(&self, hasher: &mut H) where
         H: Hasher,
     {
-        self.joker_cell.hash(hasher);
-        sorted(self.game_cells.iter()).hash(hasher);
-        sorted(self.free_cells.iter()).hash(hasher);
-        sorted(self.goal_cells.iter()).hash(hasher);
+        hasher.write_u64(self.zobrist);
     }
 }
 
+/// What a deal is shuffled from. Seeds are written as Z85 text, so they can be shared.
+#[derive(Clone)]
 pub struct Seed {
     key: [u8; 32],
 }
 
+/// Why `Seed::from_string` refused a seed.
+#[derive(Debug)]
+pub enum SeedError {
+    /// The seed isn't valid Z85, eg it contains a character outside the Z85 alphabet.
+    InvalidZ85(FromZ85Error),
+    /// The seed decodes to the given number of bytes, rather than the 32 a seed needs.
+    WrongLength(usize),
+}
+
+impl fmt::Display for SeedError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            SeedError::InvalidZ85(err) => write!(formatter, "not a valid seed ({})", err),
+            // Every 4 bytes of Z85 data is written as 5 characters.
+            SeedError::WrongLength(bytes) =>
+                write!(formatter, "seeds are 40 characters long, not {}", bytes * 5 / 4),
+        }
+    }
+}
+
 impl Seed {
-    pub fn from_string(seed: &str) -> Seed {
-        let bytes = seed.from_z85().unwrap();
+    /// Parse a seed written by `to_string`.
+    pub fn from_string(seed: &str) -> Result<Seed, SeedError> {
+        let bytes = seed.from_z85().map_err(SeedError::InvalidZ85)?;
         let mut array = [0; 32];
-        let bytes = &bytes[..array.len()]; // panics if not enough data
-        array.copy_from_slice(bytes);
-        Seed {key: array}
+        if bytes.len() != array.len() {
+            return Err(SeedError::WrongLength(bytes.len()));
+        }
+        array.copy_from_slice(&bytes);
+        Ok(Seed {key: array})
     }
 
+    /// The seed written as Z85 text, which `from_string` reads back.
     pub fn to_string(&self) -> String {
         self.key.to_z85().unwrap()
     }
 
+    /// A seed for a random deal.
     pub fn random() -> Seed {
         Seed {key: thread_rng().gen()}
     }
@@ -515,17 +1112,19 @@ impl fmt::Display for Seed {
     }
 }
 
-fn create_deck() -> Vec<Card> {
-    let mut vec: Vec<Card> = Vec::with_capacity(40);
-    for suit in vec![Suit::Black, Suit::Green, Suit::Red] {
-        for _ in 0..4 {
+fn create_deck(rules: &RuleSet) -> Vec<Card> {
+    let mut vec: Vec<Card> = Vec::with_capacity(rules.suits * (rules.dragons + rules.ranks as usize) + 1);
+    for &suit in rules.suits_in_play() {
+        for _ in 0..rules.dragons {
             vec.push(Card::DragonCard{suit});
         }
-        for rank in 1..10 {
+        for rank in 1..=rules.ranks {
             vec.push(Card::NumberCard{suit, rank});
         }
     }
-    vec.push(Card::JokerCard);
+    if rules.joker {
+        vec.push(Card::JokerCard);
+    }
     return vec
 }
 
@@ -549,63 +1148,67 @@ mod tests {
     use std::mem;
     use super::*;
 
-    fn assert_vec_rc_ptr_eq<T>(left: &Vec<Rc<T>>, right: &Vec<Rc<T>>) {
+    fn assert_vec_arc_ptr_eq<T>(left: &Vec<Arc<T>>, right: &Vec<Arc<T>>) {
         assert_eq!(left.len(), right.len());
         for (left_elem, right_elem) in left.iter().zip(right) {
-            assert!(Rc::ptr_eq(left_elem, right_elem));
+            assert!(Arc::ptr_eq(left_elem, right_elem));
         }
     }
 
     fn empty_board() -> Board {
         Board {
-            joker_cell: Rc::new(CardCell::JokerCell{has_joker: false}),
-            free_cells: [
-                Rc::new(CardCell::FreeCell{card: None}),
-                Rc::new(CardCell::FreeCell{card: None}),
-                Rc::new(CardCell::FreeCell{card: None}),
+            rules: Arc::new(RuleSet::default()),
+            joker_cell: Arc::new(CardCell::JokerCell{has_joker: false}),
+            free_cells: vec![
+                Arc::new(CardCell::FreeCell{card: None}),
+                Arc::new(CardCell::FreeCell{card: None}),
+                Arc::new(CardCell::FreeCell{card: None}),
             ],
-            goal_cells: [
-                Rc::new(CardCell::GoalCell{top_card: None}),
-                Rc::new(CardCell::GoalCell{top_card: None}),
-                Rc::new(CardCell::GoalCell{top_card: None}),
+            goal_cells: vec![
+                Arc::new(CardCell::GoalCell{top_card: None}),
+                Arc::new(CardCell::GoalCell{top_card: None}),
+                Arc::new(CardCell::GoalCell{top_card: None}),
             ],
-            game_cells: [
-                Rc::new(CardCell::GameCell{card_stack: Vec::new()}),
-                Rc::new(CardCell::GameCell{card_stack: Vec::new()}),
-                Rc::new(CardCell::GameCell{card_stack: Vec::new()}),
-                Rc::new(CardCell::GameCell{card_stack: Vec::new()}),
-                Rc::new(CardCell::GameCell{card_stack: Vec::new()}),
-                Rc::new(CardCell::GameCell{card_stack: Vec::new()}),
-                Rc::new(CardCell::GameCell{card_stack: Vec::new()}),
-                Rc::new(CardCell::GameCell{card_stack: Vec::new()}),
+            game_cells: vec![
+                Arc::new(CardCell::GameCell{card_stack: Vec::new()}),
+                Arc::new(CardCell::GameCell{card_stack: Vec::new()}),
+                Arc::new(CardCell::GameCell{card_stack: Vec::new()}),
+                Arc::new(CardCell::GameCell{card_stack: Vec::new()}),
+                Arc::new(CardCell::GameCell{card_stack: Vec::new()}),
+                Arc::new(CardCell::GameCell{card_stack: Vec::new()}),
+                Arc::new(CardCell::GameCell{card_stack: Vec::new()}),
+                Arc::new(CardCell::GameCell{card_stack: Vec::new()}),
             ],
+            zobrist: 0,
         }
     }
 
-    fn add_game_card(board: &mut Board, card: Card, column: usize) -> Rc<Card> {
+    fn add_game_card(board: &mut Board, card: Card, column: usize) -> Arc<Card> {
         // Indiana Jones the cell from the array.
         let mut rc_game_cell = mem::replace(
             &mut board.game_cells[column],
-            Rc::new(CardCell::GameCell{card_stack: Vec::new()}),
+            Arc::new(CardCell::GameCell{card_stack: Vec::new()}),
         );
-        let rc_card = Rc::new(card);
-        match Rc::get_mut(&mut rc_game_cell) {
+        let rc_card = Arc::new(card);
+        match Arc::get_mut(&mut rc_game_cell) {
             Some(CardCell::GameCell{card_stack}) => card_stack.push(rc_card.clone()),
             _ => panic!("Non-GameCell in game_cell slot!?"),
         }
         // Set it back, now that we've mutated it.
         // Don't need to Indiana Jones, because we put the temp cell into the toilet 🚽
         board.game_cells[column] = rc_game_cell;
+        board.zobrist = board.compute_zobrist();
         rc_card
     }
 
-    fn set_free_card(board: &mut Board, card: Card, column: usize) -> Rc<Card> {
-        let rc_card = Rc::new(card);
-        board.free_cells[column] = Rc::new(CardCell::FreeCell{card: Some(rc_card.clone())});
+    fn set_free_card(board: &mut Board, card: Card, column: usize) -> Arc<Card> {
+        let rc_card = Arc::new(card);
+        board.free_cells[column] = Arc::new(CardCell::FreeCell{card: Some(rc_card.clone())});
+        board.zobrist = board.compute_zobrist();
         rc_card
     }
 
-    fn get_card_stack_vec(board: &Board, column: usize) -> &Vec<Rc<Card>> {
+    fn get_card_stack_vec(board: &Board, column: usize) -> &Vec<Arc<Card>> {
         match &*board.game_cells[column] {
             &CardCell::GameCell{ref card_stack} => card_stack,
             _ => panic!("Non-GameCell in game_cell slot!?"),
@@ -652,7 +1255,7 @@ mod tests {
         let new_board = board.do_automoves();
         match &*new_board.goal_cells[0] {
             CardCell::GoalCell{top_card: Some(top_card)} =>
-                assert!(Rc::ptr_eq(&top_card, &green_1)),
+                assert!(Arc::ptr_eq(&top_card, &green_1)),
             CardCell::GoalCell{top_card: None} => panic!("Missing card"),
             _ => panic!("Non-GoalCell in goal_cell slot?"),
         }
@@ -661,38 +1264,39 @@ mod tests {
 
     #[test]
     /// Ensure automove will grab many cards in a single `do_automoves`, even if they start covered
+    /// or only become safe to move once others have been moved.
     fn automove_many() {
         let mut board = empty_board();
         let red_2 = add_game_card(&mut board, Card::NumberCard{suit: Suit::Red, rank: 2}, 3);
         let red_9 = add_game_card(&mut board, Card::NumberCard{suit: Suit::Red, rank: 9}, 3);
         let black_2 = add_game_card(&mut board, Card::NumberCard{suit: Suit::Black, rank: 2}, 3);
+        let green_2 = add_game_card(&mut board, Card::NumberCard{suit: Suit::Green, rank: 2}, 3);
         add_game_card(&mut board, Card::NumberCard{suit: Suit::Black, rank: 1}, 4);
         let red_1 = add_game_card(&mut board, Card::NumberCard{suit: Suit::Red, rank: 1}, 4);
-        let green_2 = add_game_card(&mut board, Card::NumberCard{suit: Suit::Green, rank: 2}, 4);
         add_game_card(&mut board, Card::NumberCard{suit: Suit::Green, rank: 1}, 4);
 
         let new_board = board.do_automoves();
 
         assert_eq!(get_card_stack_vec(&new_board, 4).len(), 0);
-        assert_vec_rc_ptr_eq(
+        assert_vec_arc_ptr_eq(
             &get_card_stack_vec(&new_board, 3),
             &vec![red_2, red_9],
         );
         match &*new_board.goal_cells[0] {
             CardCell::GoalCell{top_card: Some(top_card)} =>
-                assert!(Rc::ptr_eq(&top_card, &green_2)),
+                assert!(Arc::ptr_eq(&top_card, &green_2)),
             CardCell::GoalCell{top_card: None} => panic!("Missing card"),
             _ => panic!("Non-GoalCell in goal_cell slot?"),
         }
         match &*new_board.goal_cells[1] {
             CardCell::GoalCell{top_card: Some(top_card)} =>
-                assert!(Rc::ptr_eq(&top_card, &red_1)),
+                assert!(Arc::ptr_eq(&top_card, &red_1)),
             CardCell::GoalCell{top_card: None} => panic!("Missing card"),
             _ => panic!("Non-GoalCell in goal_cell slot?"),
         }
         match &*new_board.goal_cells[2] {
             CardCell::GoalCell{top_card: Some(top_card)} =>
-                assert!(Rc::ptr_eq(&top_card, &black_2)),
+                assert!(Arc::ptr_eq(&top_card, &black_2)),
             CardCell::GoalCell{top_card: None} => panic!("Missing card"),
             _ => panic!("Non-GoalCell in goal_cell slot?"),
         }
@@ -709,21 +1313,22 @@ mod tests {
         let black_1 = add_game_card(&mut board, Card::NumberCard{suit: Suit::Black, rank: 1}, 4);
         let green_3 = add_game_card(&mut board, Card::NumberCard{suit: Suit::Green, rank: 3}, 4);
         let green_2 = add_game_card(&mut board, Card::NumberCard{suit: Suit::Green, rank: 2}, 4);
-        add_game_card(&mut board, Card::NumberCard{suit: Suit::Green, rank: 1}, 4);
+        let green_1 = add_game_card(&mut board, Card::NumberCard{suit: Suit::Green, rank: 1}, 4);
 
         let new_board = board.do_automoves();
 
-        assert_vec_rc_ptr_eq(
+        // The green 2 stays, since the black and red 1s aren't in the goal yet.
+        assert_vec_arc_ptr_eq(
             &get_card_stack_vec(&new_board, 4),
-            &vec![black_1, green_3],
+            &vec![black_1, green_3, green_2],
         );
-        assert_vec_rc_ptr_eq(
+        assert_vec_arc_ptr_eq(
             &get_card_stack_vec(&new_board, 3),
             &vec![black_3, red_9, black_2],
         );
         match &*new_board.goal_cells[0] {
             CardCell::GoalCell{top_card: Some(top_card)} =>
-                assert!(Rc::ptr_eq(&top_card, &green_2)),
+                assert!(Arc::ptr_eq(&top_card, &green_1)),
             CardCell::GoalCell{top_card: None} => panic!("Missing card"),
             _ => panic!("Non-GoalCell in goal_cell slot?"),
         }
@@ -734,6 +1339,49 @@ mod tests {
         }
     }
 
+    #[test]
+    /// Ensure each policy moves only the cards it should, and the joker no matter what.
+    fn automove_policies() {
+        let mut board = empty_board();
+        add_game_card(&mut board, Card::NumberCard{suit: Suit::Green, rank: 2}, 0);
+        add_game_card(&mut board, Card::NumberCard{suit: Suit::Green, rank: 1}, 0);
+        add_game_card(&mut board, Card::JokerCard, 1);
+        board.goal_cells[1] = Arc::new(CardCell::GoalCell{
+            top_card: Some(Arc::new(Card::NumberCard{suit: Suit::Black, rank: 1})),
+        });
+        board.goal_cells[2] = Arc::new(CardCell::GoalCell{
+            top_card: Some(Arc::new(Card::NumberCard{suit: Suit::Red, rank: 3})),
+        });
+
+        let game = board.do_automoves_with(AutoMovePolicy::GameAccurate);
+        assert_eq!(get_card_stack_vec(&game, 0).len(), 0);
+        assert_eq!(*game.goal_cells[0].top().unwrap(), Card::NumberCard{suit: Suit::Green, rank: 2});
+
+        let conservative = board.do_automoves_with(AutoMovePolicy::Conservative);
+        assert_eq!(get_card_stack_vec(&conservative, 0).len(), 1);
+        assert_eq!(*conservative.goal_cells[0].top().unwrap(), Card::NumberCard{suit: Suit::Green, rank: 1});
+
+        let none = board.do_automoves_with(AutoMovePolicy::None);
+        assert_eq!(get_card_stack_vec(&none, 0).len(), 2);
+        assert!(none.goal_cells[0].top().is_none());
+
+        for board in &[game, conservative, none] {
+            assert!(*board.joker_cell == CardCell::JokerCell{has_joker: true});
+            assert_eq!(get_card_stack_vec(board, 1).len(), 0);
+        }
+    }
+
+    #[test]
+    /// Ensure a board isn't solved while a card waits in a free cell for the goal.
+    fn is_solved() {
+        let mut board = empty_board();
+        board.joker_cell = Arc::new(CardCell::JokerCell{has_joker: true});
+        set_free_card(&mut board, Card::DragonStack, 0);
+        assert!(board.is_solved());
+        set_free_card(&mut board, Card::NumberCard{suit: Suit::Red, rank: 9}, 1);
+        assert!(!board.is_solved());
+    }
+
     #[test]
     /// Ensure you can move a stack to another stack.
     fn move_stack() {
@@ -755,7 +1403,7 @@ mod tests {
             Err(_) => panic!("did not move stack"),
         };
         assert_eq!(get_card_stack_vec(&new_board, 0).len(), 1);
-        assert_vec_rc_ptr_eq(
+        assert_vec_arc_ptr_eq(
             &get_card_stack_vec(&new_board, 1),
             &vec![black_8, black_7, green_6, red_5, black_4],
         );
@@ -787,6 +1435,61 @@ mod tests {
         ));
     }
 
+    #[test]
+    /// Ensure exposed dragons are grouped into an open free cell, and not while one is covered.
+    fn stack_dragons() {
+        let mut board = empty_board();
+        for column in 0..4 {
+            add_game_card(&mut board, Card::DragonCard{suit: Suit::Red}, column);
+        }
+        set_free_card(&mut board, Card::NumberCard{suit: Suit::Black, rank: 3}, 0);
+
+        let stacked = board.stack_dragons(Suit::Red).expect("dragons should stack");
+        assert_eq!(*stacked.free_cells[1].top().unwrap(), Card::DragonStack);
+        assert!(stacked.free_cells[2].top().is_none());
+        for column in 0..4 {
+            assert!(stacked.game_cells[column].top().is_none());
+        }
+        assert!(board.stack_dragons(Suit::Green).is_none());
+
+        add_game_card(&mut board, Card::NumberCard{suit: Suit::Green, rank: 5}, 3);
+        assert!(board.stack_dragons(Suit::Red).is_none());
+    }
+
+    #[test]
+    /// Ensure dragons are grouped into a free cell holding one of them, even with every free cell
+    /// full, and not into one holding a dragon of another suit.
+    fn stack_dragons_onto_free_dragon() {
+        let mut board = empty_board();
+        for column in 0..3 {
+            add_game_card(&mut board, Card::DragonCard{suit: Suit::Green}, column);
+        }
+        set_free_card(&mut board, Card::DragonCard{suit: Suit::Red}, 0);
+        set_free_card(&mut board, Card::NumberCard{suit: Suit::Black, rank: 3}, 1);
+        set_free_card(&mut board, Card::DragonCard{suit: Suit::Green}, 2);
+
+        let stacked = board.stack_dragons(Suit::Green).expect("dragons should stack");
+        assert_eq!(*stacked.free_cells[0].top().unwrap(), Card::DragonCard{suit: Suit::Red});
+        assert_eq!(*stacked.free_cells[2].top().unwrap(), Card::DragonStack);
+
+        // The free cell holding the dragon is chosen over an open one.
+        set_free_card(&mut board, Card::NumberCard{suit: Suit::Black, rank: 3}, 0);
+        board.free_cells[1] = Arc::new(CardCell::FreeCell{card: None});
+        let stacked = board.stack_dragons(Suit::Green).expect("dragons should stack");
+        assert!(stacked.free_cells[1].top().is_none());
+        assert_eq!(*stacked.free_cells[2].top().unwrap(), Card::DragonStack);
+
+        // Every free cell is full, and none holds a red dragon.
+        let mut board = empty_board();
+        for column in 0..4 {
+            add_game_card(&mut board, Card::DragonCard{suit: Suit::Red}, column);
+        }
+        for column in 0..3 {
+            set_free_card(&mut board, Card::DragonCard{suit: Suit::Green}, column);
+        }
+        assert!(board.stack_dragons(Suit::Red).is_none());
+    }
+
     #[test]
     /// Ensure you can't move a DragonStack at all
     fn cant_move_dragon_stack() {
@@ -799,6 +1502,24 @@ mod tests {
         ));
     }
 
+    #[test]
+    /// Ensure cards can't be taken back out of a goal cell, rather than panicking.
+    fn cant_move_from_goal() {
+        let mut board = empty_board();
+        board.goal_cells[0] = Arc::new(CardCell::GoalCell{
+            top_card: Some(Arc::new(Card::NumberCard{suit: Suit::Black, rank: 1})),
+        });
+        assert_is_invalid_move(&board.move_stack(
+            &CardCellIndex::GoalCellIndex(0),
+            &CardCellIndex::FreeCellIndex(0),
+        ));
+        assert!(board.apply_move(&Move::MoveStack{
+            source: CardCellIndex::GoalCellIndex(0),
+            dest: CardCellIndex::GameCellIndex(0),
+            height: 1,
+        }).is_none());
+    }
+
     #[test]
     /// Ensure you can stack a NumberCard from a FreeCell on a NumberCard in a GameCell.
     fn move_stack_via_free() {
@@ -814,7 +1535,7 @@ mod tests {
             Err(_) => panic!("did not move stack"),
         };
 
-        assert_vec_rc_ptr_eq(
+        assert_vec_arc_ptr_eq(
             &get_card_stack_vec(&new_board, 0),
             &vec![green_5, black_4],
         );
@@ -834,7 +1555,7 @@ mod tests {
             Err(_) => panic!("did not move stack"),
         };
 
-        assert_vec_rc_ptr_eq(
+        assert_vec_arc_ptr_eq(
             &get_card_stack_vec(&new_board, 0),
             &vec![black_4],
         );
@@ -854,14 +1575,208 @@ mod tests {
             Ok(new_board) => new_board,
             Err(_) => panic!("did not move stack"),
         };
-        assert_vec_rc_ptr_eq(
+        assert_vec_arc_ptr_eq(
             &get_card_stack_vec(&new_board, 0),
             &vec![black_8],
         );
-        assert_vec_rc_ptr_eq(
+        assert_vec_arc_ptr_eq(
             &get_card_stack_vec(&new_board, 1),
             &vec![black_4],
         );
     }
 
+    #[test]
+    /// Ensure freshly dealt boards, and the boards automoves make of them, are valid.
+    fn validate_dealt() {
+        for _ in 0..20 {
+            let (board, _) = Board::deal();
+            assert_eq!(board.validate(), Vec::new());
+            assert_eq!(board.do_automoves().validate(), Vec::new());
+        }
+    }
+
+    #[test]
+    /// Ensure boards dealt by other rule sets are valid by those rules, and laid out for them.
+    fn validate_dealt_with_rules() {
+        let rules = RuleSet::from_string("free=4,columns=9,suits=4,joker=no").unwrap();
+        for _ in 0..20 {
+            let board = Board::deal_with_rules(&Seed::random(), &rules);
+            assert_eq!(board.free_cells().len(), 4);
+            assert_eq!(board.goal_cells().len(), 4);
+            assert_eq!(board.game_cells().len(), 9);
+            assert_eq!(board.validate(), Vec::new());
+            assert_eq!(board.do_automoves().validate(), Vec::new());
+        }
+
+        let rules = RuleSet::from_string("suits=2,ranks=5,dragons=0").unwrap();
+        let board = Board::deal_with_rules(&Seed::random(), &rules);
+        let cards: usize = board.game_cells().iter().map(|cell| cell.len()).sum();
+        assert_eq!(cards, 2 * 5 + 1);
+        assert_eq!(board.validate(), Vec::new());
+
+        // Red isn't dealt, and neither is a black 6.
+        let board = Board::with_rules(
+            &rules,
+            vec![None, None, None],
+            true,
+            vec![
+                Some(Card::NumberCard{suit: Suit::Red, rank: 3}),
+                Some(Card::NumberCard{suit: Suit::Black, rank: 6}),
+            ],
+            (0..8).map(|column| match column {
+                0 => (1..=5).map(|rank| Card::NumberCard{suit: Suit::Black, rank}).collect(),
+                1 => (1..=5).map(|rank| Card::NumberCard{suit: Suit::Green, rank}).collect(),
+                _ => Vec::new(),
+            }).collect(),
+        ).unwrap();
+        assert_eq!(board.validate(), vec![
+            Violation::UnexpectedGoalCard(Card::NumberCard{suit: Suit::Red, rank: 3}),
+            Violation::UnexpectedGoalCard(Card::NumberCard{suit: Suit::Black, rank: 6}),
+        ]);
+    }
+
+    #[test]
+    fn rule_set_from_string() {
+        assert_eq!(RuleSet::from_string("").unwrap(), RuleSet::default());
+        let rules = RuleSet::from_string(" free=4, suits=4 ,joker=no").unwrap();
+        assert_eq!(rules, RuleSet{free_cells: 4, suits: 4, joker: false, ..RuleSet::default()});
+        assert_eq!(RuleSet::from_string(&rules.to_string()).unwrap(), rules);
+
+        match RuleSet::from_string("free") {
+            Err(RuleSetError::Malformed(_)) => (),
+            _ => panic!("expected a rule without a value to be refused"),
+        }
+        match RuleSet::from_string("decks=2") {
+            Err(RuleSetError::UnknownRule(_)) => (),
+            _ => panic!("expected an unknown rule to be refused"),
+        }
+        match RuleSet::from_string("suits=5") {
+            Err(RuleSetError::InvalidValue{..}) => (),
+            _ => panic!("expected too many suits to be refused"),
+        }
+        match RuleSet::from_string("joker=maybe") {
+            Err(RuleSetError::InvalidValue{..}) => (),
+            _ => panic!("expected a bad joker value to be refused"),
+        }
+    }
+
+    #[test]
+    /// Ensure each kind of impossible board is reported.
+    fn validate_violations() {
+        let mut column = Vec::new();
+        for &suit in [Suit::Black, Suit::Green].iter() {
+            for rank in 1..10 {
+                column.push(Card::NumberCard{suit, rank});
+            }
+        }
+        column.push(Card::NumberCard{suit: Suit::Green, rank: 1});
+        column.push(Card::NumberCard{suit: Suit::Red, rank: 5});
+        for _ in 0..5 {
+            column.push(Card::DragonCard{suit: Suit::Black});
+        }
+        for _ in 0..2 {
+            column.push(Card::DragonCard{suit: Suit::Green});
+        }
+        let board = Board::new(
+            vec![Some(Card::DragonStack), None, None],
+            false,
+            vec![
+                Some(Card::NumberCard{suit: Suit::Red, rank: 9}),
+                Some(Card::NumberCard{suit: Suit::Red, rank: 3}),
+                Some(Card::DragonCard{suit: Suit::Green}),
+            ],
+            vec![
+                vec![Card::DragonStack],
+                column,
+                Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(),
+            ],
+        ).unwrap();
+        assert_eq!(
+            board.validate(),
+            vec![
+                Violation::DuplicateGoalSuit(Suit::Red),
+                Violation::MisplacedCard{
+                    index: CardCellIndex::GoalCellIndex(2),
+                    card: Card::DragonCard{suit: Suit::Green},
+                },
+                Violation::MisplacedCard{index: CardCellIndex::GameCellIndex(0), card: Card::DragonStack},
+                Violation::DuplicateCard(Card::NumberCard{suit: Suit::Green, rank: 1}),
+                Violation::GoalCardInPlay(Card::NumberCard{suit: Suit::Red, rank: 5}),
+                Violation::TooManyDragons{suit: Suit::Black, count: 5},
+                Violation::MissingDragons{suit: Suit::Green, count: 2},
+                Violation::MissingJoker,
+            ],
+        );
+    }
+
+    #[test]
+    /// Ensure the hash kept up to date as cards move matches one worked out from scratch, and
+    /// doesn't depend on the order of the game cells.
+    fn zobrist_hash() {
+        let mut board = Board::deal_seeded(&Seed::from_string("8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&").unwrap());
+        assert_eq!(board.zobrist_hash(), board.compute_zobrist());
+        for _ in 0..20 {
+            board = board.do_automoves();
+            assert_eq!(board.zobrist_hash(), board.compute_zobrist());
+            let next: Vec<Board> = ::solver::next_states(&board, AutoMovePolicy::None).into_iter()
+                .map(|(_, next)| next).collect();
+            for next in next.iter() {
+                assert_eq!(next.zobrist_hash(), next.compute_zobrist());
+            }
+            match next.into_iter().last() {
+                Some(next) => board = next,
+                None => break,
+            }
+        }
+
+        let mut reversed = board.clone();
+        reversed.game_cells.reverse();
+        reversed.free_cells.reverse();
+        assert_eq!(reversed.compute_zobrist(), board.zobrist_hash());
+        assert!(reversed == board);
+    }
+
+    #[test]
+    /// Ensure boards can be shared between threads, for the parallel solver.
+    fn send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Board>();
+    }
+
+    #[test]
+    /// Ensure boards with the wrong number of cells are refused rather than panicking.
+    fn new_wrong_cell_count() {
+        assert_eq!(
+            Board::new(vec![None, None], false, vec![None, None, None], vec![Vec::new(); 8]).err(),
+            Some(BoardError::WrongCellCount{kind: "free", expected: 3, found: 2}),
+        );
+        assert_eq!(
+            Board::new(vec![None, None, None], false, vec![None; 4], vec![Vec::new(); 8]).err(),
+            Some(BoardError::WrongCellCount{kind: "goal", expected: 3, found: 4}),
+        );
+        assert_eq!(
+            Board::new(vec![None, None, None], false, vec![None, None, None], vec![Vec::new(); 7]).err(),
+            Some(BoardError::WrongCellCount{kind: "game", expected: 8, found: 7}),
+        );
+    }
+
+    #[test]
+    /// Ensure seeds round-trip, and malformed seeds are refused rather than panicking.
+    fn seed_from_string() {
+        let seed = Seed::random();
+        assert_eq!(Seed::from_string(&seed.to_string()).expect("should parse").key, seed.key);
+
+        match Seed::from_string("8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p") {
+            Err(SeedError::InvalidZ85(_)) => (),
+            _ => panic!("expected a seed of bad length to be refused"),
+        }
+        match Seed::from_string("8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p\"") {
+            Err(SeedError::InvalidZ85(_)) => (),
+            _ => panic!("expected a seed with a bad character to be refused"),
+        }
+        match Seed::from_string(&"8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&"[..35]) {
+            Err(SeedError::WrongLength(28)) => (),
+            _ => panic!("expected a short seed to be refused"),
+        }
+    }
 }
@@ -1,4 +1,4 @@
-use ::board::{Suit, Card, CardCell, Board};
+use ::board::{Suit, Card, CardCell, CardCellIndex, Board, Move};
 use ::util;
 
 
@@ -74,13 +74,18 @@ pub fn display_highlighted_cell(card_cell: &CardCell, height: u8) -> String {
     }
 }
 
+/// The joker cell, or a blank where it would be if the board's rules deal no joker.
+pub fn display_joker_cell(board: &Board) -> String {
+    if board.rules().joker {display_cell(board.joker_cell())} else {String::from(" ")}
+}
+
 pub fn display_board(board: &Board) -> String {
     let mut s = String::new();
     for cell in board.free_cells().iter() {
         s.push_str(&display_cell(cell));
     }
     s.push_str("  ");
-    s.push_str(&display_cell(board.joker_cell()));
+    s.push_str(&display_joker_cell(board));
     s.push_str(" ");
     for cell in board.goal_cells().iter() {
         s.push_str(&display_cell(cell));
@@ -93,6 +98,32 @@ pub fn display_board(board: &Board) -> String {
     return s;
 }
 
+fn cell_name(index: &CardCellIndex) -> String {
+    match index {
+        // Which goal cell doesn't matter much to the player.
+        CardCellIndex::GoalCellIndex(_) => String::from("the goal"),
+        _ => index.to_string(),
+    }
+}
+
+/// Describes a move in plain English, eg "move 3-card stack from column 2 to column 6".
+///
+/// `board` is the board the move is made on, and is used to name single cards being moved.
+pub fn display_move(board: &Board, mv: &Move) -> String {
+    match mv {
+        Move::GroupDragons{suit} => format!("group {} dragons", suit),
+        Move::MoveStack{source, dest, height: 1} => format!(
+            "move {} from {} to {}",
+            board.get_cell(source).top().map_or(String::from("nothing"), |card| card.to_string()),
+            cell_name(source),
+            cell_name(dest),
+        ),
+        Move::MoveStack{source, dest, height} => format!(
+            "move {}-card stack from {} to {}", height, cell_name(source), cell_name(dest),
+        ),
+    }
+}
+
 fn term_color(suit: Suit, text: String) -> String {
     format!(
         "\x1b[{}m{}\x1b[39m",
@@ -100,6 +131,7 @@ fn term_color(suit: Suit, text: String) -> String {
             Suit::Black => 90,
             Suit::Green => 32,
             Suit::Red => 31,
+            Suit::Yellow => 33,
         },
         text,
     )
@@ -112,6 +144,7 @@ fn term_highlight(suit: Suit, text: String) -> String {
             Suit::Black => 90,
             Suit::Green => 32,
             Suit::Red => 31,
+            Suit::Yellow => 33,
         },
         text,
     )
@@ -1,64 +1,160 @@
-extern crate getch;
+use std::fs::File;
+use std::io::{self, Write};
+use std::mem;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use std::cmp::max;
-use std::rc::Rc;
-
-use ::board::{Board, CardCell, Card, CardCellIndex, MoveStackError};
-use ::display::{display_cell, display_highlighted_cell, dim, no_dim};
+use ::board::{AutoMovePolicy, Board, CardCell, Card, CardCellIndex, Move, MoveStackError, RuleSet, Seed};
+use ::display::{display_board, display_cell, display_highlighted_cell, display_joker_cell, display_move, dim, no_dim};
+use ::keys::{Key, Keys};
+use ::save::{self, SavedGame};
+use ::screen::Screen;
+use ::solver::{self, SolveOutcome, SolverConfig};
 use ::util;
 
+/// How long to look for a solution when the player asks for a hint.
+const HINT_MAX_TIME: Duration = Duration::from_secs(3);
+/// How soon a second click on the same cell has to come to count as a double-click.
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(400);
+
 #[derive(Debug)]
 enum GameMode {
     SelectSource,
-    SelectDestination{cursor: u8},
+    /// `height` is how many cards to move, if the player clicked a card partway down the stack.
+    SelectDestination{cursor: u8, height: Option<u8>},
     ChooseStackHeight{cursor: u8, height: u8, max_height: u8},
 }
 
-/// Get the display height of the specified GameCell.
-///
-/// This is almost, but not quite, the number of cards in the cell. When the cell is empty this
-/// is one, for the dash we draw to denote an empty cell.
-fn game_cell_height(game_cell: &CardCell) -> u8 {
-    match game_cell {
-        CardCell::GameCell{card_stack} => max(card_stack.len() as u8, 1),
-        _ => panic!("`height()` is only defined for GameCells"),
-    }
-}
-
 /// Human-playable board representation.
 pub struct Game {
     board: Board,
+    /// The seed the game was dealt from.
+    seed: Seed,
+    /// Which cell the cursor is on: the free cells count from 1, then the goal cells, then the
+    /// game cells.
     cursor: u8,
     mode: GameMode,
+    /// The move suggested by the last hint, until the next key press.
+    hint: Option<Move>,
+    /// Which cards are moved to the goal without the player moving them.
+    automoves: AutoMovePolicy,
+    /// Something to tell the player, until the next key press.
+    message: Option<String>,
+    /// Whether to show the controls below the board.
+    show_controls: bool,
+    /// Each of the player's moves, oldest first, and the board before it. Each move includes the
+    /// automoves that followed it.
+    history: Vec<(Move, Board)>,
+    /// Each undone move, most recently undone last, and the board after it.
+    future: Vec<(Move, Board)>,
+    /// Where to save the game to.
+    save_path: Option<String>,
+    /// How long the game was played for before it was last resumed.
+    elapsed_before: Duration,
+    resumed: Instant,
+    /// The cell last clicked, and when, to spot double-clicks.
+    last_click: Option<(u8, Instant)>,
 }
 impl Game {
-    pub fn new(board: Board) -> Game {
+    /// A game dealt from `seed` and played by `rules`.
+    pub fn with_rules(seed: Seed, rules: &RuleSet) -> Game {
+        let board = Board::deal_with_rules(&seed, rules);
         Game{
+            // The middle column.
+            cursor: (rules.free_cells + rules.suits + rules.columns / 2) as u8 + 1,
             board,
-            cursor: 11,
-            mode: GameMode::SelectSource
+            seed,
+            mode: GameMode::SelectSource,
+            hint: None,
+            automoves: AutoMovePolicy::default(),
+            message: None,
+            show_controls: true,
+            history: Vec::new(),
+            future: Vec::new(),
+            save_path: None,
+            elapsed_before: Duration::from_secs(0),
+            resumed: Instant::now(),
+            last_click: None,
         }
     }
 
-    fn print(&self) {
-        let mut s = String::new();
+    /// Pick up a saved game where it was left.
+    pub fn resume(saved: SavedGame) -> Game {
+        let start = save::starting_board(&saved.seed, &saved.rules, saved.automoves);
+        let mut boards = save::replay(start, &saved.moves, saved.automoves)
+            .expect("saved games are checked when they're loaded");
+        let board = boards.pop().expect("never empty");
+        let undone = save::replay(board.clone(), &saved.undone, saved.automoves)
+            .expect("saved games are checked when they're loaded");
+        Game{
+            history: saved.moves.iter().cloned().zip(boards).collect(),
+            future: saved.undone.iter().cloned().zip(undone.into_iter().skip(1)).rev().collect(),
+            board,
+            automoves: saved.automoves,
+            cursor: saved.cursor,
+            elapsed_before: saved.elapsed,
+            ..Game::with_rules(saved.seed, &saved.rules)
+        }
+    }
+
+    /// Save the game to `path` whenever asked to, and when the player quits.
+    pub fn save_to(&mut self, path: String) {
+        self.save_path = Some(path);
+    }
+
+    /// Move cards to the goal automatically according to `policy`, rather than as the game does.
+    pub fn set_automoves(&mut self, policy: AutoMovePolicy) {
+        self.automoves = policy;
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.elapsed_before + self.resumed.elapsed()
+    }
 
-        let mut top_row = [" "; 10];
-        match self.cursor {
-            1...3 => top_row[self.cursor as usize - 1] = "v",
-            4...6 => top_row[self.cursor as usize + 3] = "v",
-            _ => (),
+    fn to_saved(&self) -> SavedGame {
+        SavedGame{
+            seed: self.seed.clone(),
+            rules: self.board.rules().clone(),
+            board: self.board.clone(),
+            moves: self.history.iter().map(|&(mv, _)| mv).collect(),
+            undone: self.future.iter().rev().map(|&(mv, _)| mv).collect(),
+            elapsed: self.elapsed(),
+            automoves: self.automoves,
+            cursor: self.cursor,
         }
+    }
 
-        let should_dim;
-        match self.mode {
-            GameMode::SelectDestination{cursor: cursor @ 1...3} => {
-                top_row[cursor as usize - 1] = selector_color!("v");
-                should_dim = false;
-            },
-            GameMode::ChooseStackHeight{..} => should_dim = true,
-            _ => should_dim = false,
+    fn save(&mut self) {
+        let path = match self.save_path {
+            Some(ref path) => path.clone(),
+            None => return,
+        };
+        let result = File::create(&path)
+            .and_then(|mut file| file.write_all(save::format_saved_game(&self.to_saved()).as_bytes()));
+        self.message = Some(match result {
+            Ok(()) => format!("Saved to {}.", path),
+            Err(err) => format!("Couldn't save to {}: {}", path, err),
+        });
+    }
+
+    /// Draw the board, cursors and all, with a status line and maybe the controls below it.
+    fn render(&self) -> String {
+        let mut s = String::new();
+        let marked = self.marked_cursors();
+
+        let rules = self.board.rules();
+        let mut top_row = vec![" "; rules.free_cells + 4 + rules.suits];
+        if let Some(i) = self.top_row_index(self.cursor) {
+            top_row[i] = "v";
+        }
+        for &cursor in marked.iter() {
+            if let Some(i) = self.top_row_index(cursor) {
+                top_row[i] = selector_color!("v");
+            }
         }
+
+        let should_dim = matches!(self.mode, GameMode::ChooseStackHeight{..});
         s.push_str(&top_row.join(""));
         s.push_str("\n");
 
@@ -66,7 +162,7 @@ impl Game {
             s.push_str(&display_cell(cell));
         }
         s.push_str("  ");
-        s.push_str(&display_cell(self.board.joker_cell()));
+        s.push_str(&display_joker_cell(&self.board));
         s.push_str(" ");
         for cell in self.board.goal_cells().iter() {
             s.push_str(&display_cell(cell));
@@ -75,110 +171,205 @@ impl Game {
 
         let mut strings: Vec<_> = self.board.game_cells().iter().enumerate().map(|(i, cell)|
             match self.mode {
-                GameMode::ChooseStackHeight{height, cursor, ..} if cursor as usize - 7 == i => {
+                GameMode::ChooseStackHeight{height, cursor, ..} if cursor == self.first_column() + i as u8 => {
                     display_highlighted_cell(cell, height)},
                 _ => display_cell(cell),
             }
         ).collect();
-        let cursor_collision = match self.mode {
-            GameMode::SelectDestination{cursor: cursor @ 7...14} |
-            GameMode::ChooseStackHeight{cursor: cursor @ 7...14, ..} => {
-                strings[cursor as usize - 7].push_str(
-                    &format!("\n{}", no_dim(selector_color!("^").to_string(), should_dim))
-                );
-                cursor == self.cursor
-            },
-            _ => false,
-        };
-        if !cursor_collision {
-            match self.cursor {
-                7...14 => strings[self.cursor as usize - 7].push_str(
-                    &format!("\n{}", no_dim("^".to_string(), should_dim))
-                ),
-                _ => (),
+        for (i, string) in strings.iter_mut().enumerate() {
+            let cursor = self.first_column() + i as u8;
+            if marked.contains(&cursor) {
+                string.push_str(&format!("\n{}", no_dim(selector_color!("^").to_string(), should_dim)));
+            }
+            else if cursor == self.cursor {
+                string.push_str(&format!("\n{}", no_dim("^".to_string(), should_dim)));
             }
         }
         s.push_str(&util::join_vertical(strings));
-        if self.should_buffer_height() {
-            s.push_str("\n");
-        }
         if should_dim {
             s = dim(s);
         }
-        println!("{}", s);
+
+        let seconds = self.elapsed().as_secs();
+        s.push_str(&format!("\n{} moves  {}:{:02}", self.history.len(), seconds / 60, seconds % 60));
+        if let Some(ref message) = self.message {
+            s.push_str("  ");
+            s.push_str(message);
+        }
+        s.push('\n');
+        if self.show_controls {
+            s.push('\n');
+            s.push_str(Game::controls());
+        }
+        s
     }
 
-    /// Returns true if we should print an extra newline at the end of the board display.
-    ///
-    /// As we move the cursor around the board we affect the height of the display, specifically
-    /// as the cursor passes through the tallest columns. We compensate for this by printing a
-    /// newline when no cursor is on one of the tallest columns.
-    fn should_buffer_height(&self) -> bool {
-        let game_cells = self.board.game_cells();
-        let max_cell_height = game_cells.iter().map(|col| game_cell_height(&*col)).max().expect("it's an array");
+    /// Where in the top row the marker for the given cursor value goes, if it goes there at all.
+    fn top_row_index(&self, cursor: u8) -> Option<usize> {
+        if self.is_column(cursor) {None} else {Some(self.cursor_x(cursor))}
+    }
+
+    /// The cursor value of the leftmost game cell. Every cursor value before it is a free or goal
+    /// cell.
+    fn first_column(&self) -> u8 {
+        let rules = self.board.rules();
+        (rules.free_cells + rules.suits) as u8 + 1
+    }
+
+    /// The cursor value of the rightmost game cell, and so the highest cursor value.
+    fn last_column(&self) -> u8 {
+        self.first_column() + self.board.rules().columns as u8 - 1
+    }
 
-        if self.cursor >= 7 && game_cell_height(&*game_cells[self.cursor as usize - 7]) == max_cell_height {
-            return false;
+    fn is_column(&self, cursor: u8) -> bool {
+        cursor >= self.first_column()
+    }
+
+    fn is_goal(&self, cursor: u8) -> bool {
+        cursor as usize > self.board.rules().free_cells && !self.is_column(cursor)
+    }
+
+    /// Which character of its row the cell for the given cursor value is drawn at.
+    fn cursor_x(&self, cursor: u8) -> usize {
+        let free_cells = self.board.rules().free_cells;
+        match self.cursor_to_cci(cursor) {
+            CardCellIndex::FreeCellIndex(n) => n,
+            // Past the free cells, a gap, the joker cell and another gap.
+            CardCellIndex::GoalCellIndex(n) => free_cells + 4 + n,
+            // Past the leading space.
+            CardCellIndex::GameCellIndex(n) => n + 1,
         }
+    }
+
+    /// The cursor value among `cursors` whose cell is drawn closest to above or below the cell
+    /// for `cursor`, favouring the leftmost.
+    fn nearest<I: Iterator<Item=u8>>(&self, cursor: u8, cursors: I) -> u8 {
+        let x = self.cursor_x(cursor) as isize;
+        cursors.min_by_key(|&other| (self.cursor_x(other) as isize - x).abs())
+            .expect("every row has a cell")
+    }
+
+    /// Cursor values to mark in the selector color: the selected source, and the hinted move.
+    fn marked_cursors(&self) -> Vec<u8> {
+        let mut marked = Vec::new();
         match self.mode {
-            GameMode::ChooseStackHeight{cursor, ..} | GameMode::SelectDestination{cursor}
-            if cursor >= 7 && game_cell_height(&*game_cells[cursor as usize - 7]) == max_cell_height
-                => false,
-            _ => true,
+            GameMode::SelectDestination{cursor, ..} | GameMode::ChooseStackHeight{cursor, ..} =>
+                marked.push(cursor),
+            GameMode::SelectSource => (),
         }
+        match self.hint {
+            Some(Move::MoveStack{source, dest, ..}) => {
+                marked.push(self.cci_to_cursor(&source));
+                marked.push(self.cci_to_cursor(&dest));
+            },
+            // Mark each of the dragons to group.
+            Some(Move::GroupDragons{suit}) => marked.extend((1..=self.last_column()).filter(|&cursor|
+                self.cell_at(cursor).top().is_some_and(|card| *card == Card::DragonCard{suit})
+            )),
+            None => (),
+        }
+        marked
     }
 
     pub fn play(&mut self) {
-        self.print();
-        self.board = self.board.do_automoves();
-        self.print();
-        let g = getch::Getch::new();
+        self.board = self.board.do_automoves_with(self.automoves);
+        let mut keys = Keys::new();
+        let mut screen = Screen::new();
+        screen.draw(&self.render());
         while !self.board.is_solved() {
-            let chr = match g.getch() {
-                Ok(value) => value,
+            let key = match keys.next() {
+                Ok(key) => key,
                 Err(msg) => {
+                    self.save();
+                    drop(screen);
                     println!("Ok guess we're done ({})", msg);
                     return;
                 }
             };
-            match chr as char {
-                '?' => Game::print_controls(),
-                'g' | 'G' => self.stack_dragons(),
-                'w' | 'W' => self.move_cursor_up(),
-                'a' | 'A' => self.move_cursor_left(),
-                's' | 'S' => self.move_cursor_down(),
-                'd' | 'D' => self.move_cursor_right(),
-                'c' | 'C' => self.cancel(),
-                num @ '1' ... '8' => self.jump_to(num as u8 - '0' as u8),
-                ' ' => self.select(),
+            self.hint = None;
+            self.message = None;
+            match key {
+                Key::Char('?') => self.show_controls = !self.show_controls,
+                Key::Char('h') | Key::Char('H') => self.hint(),
+                Key::Char('u') | Key::Char('U') => self.undo(),
+                Key::Char('r') | Key::Char('R') => self.redo(),
+                Key::Char('v') | Key::Char('V') => self.save(),
+                Key::Char('q') | Key::Char('Q') | Key::Interrupt => {
+                    self.save();
+                    drop(screen);
+                    if let Some(ref message) = self.message {
+                        println!("{}", message);
+                    }
+                    return;
+                },
+                Key::Char('g') | Key::Char('G') => self.stack_dragons(),
+                Key::Char('w') | Key::Char('W') | Key::Up => self.move_cursor_up(),
+                Key::Char('a') | Key::Char('A') | Key::Left => self.move_cursor_left(),
+                Key::Char('s') | Key::Char('S') | Key::Down => self.move_cursor_down(),
+                Key::Char('d') | Key::Char('D') | Key::Right => self.move_cursor_right(),
+                Key::Char('c') | Key::Char('C') | Key::Escape => self.cancel(),
+                Key::Char(num @ '1' ..= '9') => self.jump_to(num as u8 - '0' as u8),
+                Key::Home => self.jump_to(1),
+                Key::End => self.jump_to_end(),
+                Key::Char(' ') | Key::Enter => self.select(),
+                Key::Click{x, y} => self.click(x, y, Instant::now()),
                 _ => (),
             }
-            self.print();
+            screen.draw(&self.render());
         }
+        drop(screen);
+        self.finish();
+    }
+
+    /// Save the won game, so resuming the save doesn't go back to an earlier position, and say so.
+    fn finish(&mut self) {
+        self.save();
+        let seconds = self.elapsed().as_secs();
+        println!("{}", display_board(&self.board));
         println!("You wiiiin");
+        println!("{} moves in {}:{:02}", self.history.len(), seconds / 60, seconds % 60);
+        let _ = io::stdout().flush();
     }
 
     fn select(&mut self) {
         match self.mode {
             GameMode::SelectSource => {
                 // Can't select goal cells
-                if 4 <= self.cursor && self.cursor <= 6 {return;}
+                if self.is_goal(self.cursor) {return;}
                 // if selection is none or a DragonStack, don't select
                 if let Some(rc_card) = self.cell_at(self.cursor).top() {
                     match &*rc_card {
                         Card::DragonStack => (),
-                        _ => self.mode = GameMode::SelectDestination{cursor: self.cursor},
+                        _ => self.mode = GameMode::SelectDestination{cursor: self.cursor, height: None},
                     }
                 }
             },
-            GameMode::SelectDestination{cursor} => {
+            GameMode::SelectDestination{cursor, height: Some(height)} => {
+                let mv = Move::MoveStack{
+                    source: self.cursor_to_cci(cursor),
+                    dest: self.cursor_to_cci(self.cursor),
+                    height,
+                };
+                if let Some(board) = self.board.apply_move(&mv) {
+                    self.make_move(mv, board);
+                }
+                self.mode = GameMode::SelectSource;
+            },
+            GameMode::SelectDestination{cursor, height: None} => {
                 let new_board = self.board.move_stack(
-                    &Game::cursor_to_cci(cursor),
-                    &Game::cursor_to_cci(self.cursor),
+                    &self.cursor_to_cci(cursor),
+                    &self.cursor_to_cci(self.cursor),
                 );
                 self.mode = match new_board {
                     Ok(board) => {
-                        self.board = board.do_automoves();
+                        let source = self.cursor_to_cci(cursor);
+                        let height = self.board.get_cell(&source).len() - board.get_cell(&source).len();
+                        let mv = Move::MoveStack{
+                            source,
+                            dest: self.cursor_to_cci(self.cursor),
+                            height: height as u8,
+                        };
+                        self.make_move(mv, board);
                         GameMode::SelectSource
                     },
                     Err(MoveStackError::InvalidMove) => GameMode::SelectSource,
@@ -192,54 +383,215 @@ impl Game {
             },
             GameMode::ChooseStackHeight{cursor, height, ..} => {
                 let new_board = self.board.move_n_cards(
-                    &Game::cursor_to_cci(cursor),
-                    &Game::cursor_to_cci(self.cursor),
+                    &self.cursor_to_cci(cursor),
+                    &self.cursor_to_cci(self.cursor),
                     height as usize,
                 );
                 if let Some(board) = new_board {
-                    self.board = board.do_automoves();
+                    let mv = Move::MoveStack{
+                        source: self.cursor_to_cci(cursor),
+                        dest: self.cursor_to_cci(self.cursor),
+                        height,
+                    };
+                    self.make_move(mv, board);
                 }
                 self.mode = GameMode::SelectSource
             }
         }
     }
 
+    /// Act on a click at column `x` and row `y` of the screen, counting from 1, at time `now`.
+    ///
+    /// Clicking a card selects it, along with the cards on top of it, and clicking somewhere else
+    /// then places them there. Double-clicking an exposed dragon groups it with the others.
+    fn click(&mut self, x: u16, y: u16, now: Instant) {
+        let (cursor, depth) = match self.cursor_at(x, y) {
+            Some(clicked) => clicked,
+            None => {
+                self.last_click = None;
+                return;
+            },
+        };
+        let double = match self.last_click {
+            Some((last, time)) => last == cursor && now.duration_since(time) <= DOUBLE_CLICK_TIME,
+            None => false,
+        };
+        self.last_click = if double {None} else {Some((cursor, now))};
+        let dragon = matches!(self.cell_at(cursor).top().as_deref(), Some(Card::DragonCard{..}));
+        if double && dragon {
+            self.cursor = cursor;
+            self.mode = GameMode::SelectSource;
+            self.stack_dragons();
+            return;
+        }
+
+        // Clicks below a stack are clicks on its top card.
+        let len = self.cell_at(cursor).len();
+        let height = depth.filter(|&depth| depth < len).map(|depth| (len - depth) as u8);
+        match self.mode {
+            GameMode::SelectSource => {
+                self.cursor = cursor;
+                self.select();
+                if let GameMode::SelectDestination{cursor, ..} = self.mode {
+                    self.mode = GameMode::SelectDestination{cursor, height};
+                }
+            },
+            // Clicking the selection again lets go of it.
+            GameMode::SelectDestination{cursor: source, ..} if source == cursor =>
+                self.mode = GameMode::SelectSource,
+            GameMode::SelectDestination{..} => {
+                self.cursor = cursor;
+                self.select();
+            },
+            // Clicking a card in the stack being moved picks how many to move, as does clicking
+            // below the stack for all of them.
+            GameMode::ChooseStackHeight{cursor: source, max_height, ..} if source == cursor => {
+                let height = height.unwrap_or(max_height);
+                if height <= max_height {
+                    self.mode = GameMode::ChooseStackHeight{cursor: source, height, max_height};
+                    self.select();
+                }
+            },
+            GameMode::ChooseStackHeight{..} => self.mode = GameMode::SelectSource,
+        }
+    }
+
+    /// The cursor value for the cell drawn at column `x` and row `y` of the screen, counting from
+    /// 1, and for game cells, how far down the stack the click was.
+    ///
+    /// Matches the layout from `render`: a row of markers, the free cells, joker and goal cells,
+    /// then the game cells side by side.
+    fn cursor_at(&self, x: u16, y: u16) -> Option<(u8, Option<usize>)> {
+        let (x, y) = (x.checked_sub(1)? as usize, y.checked_sub(1)? as usize);
+        let rules = self.board.rules();
+        let goals = rules.free_cells + 4;
+        if y <= 1 {
+            if x < rules.free_cells {
+                Some((x as u8 + 1, None))
+            } else if goals <= x && x < goals + rules.suits {
+                Some((self.cci_to_cursor(&CardCellIndex::GoalCellIndex(x - goals)), None))
+            } else {
+                None
+            }
+        } else if 1 <= x && x <= rules.columns {
+            let depth = y - 2;
+            let tallest = self.board.game_cells().iter().map(|cell| cell.len()).max()
+                .expect("there are always game cells");
+            // Allow for the cursor marker below the tallest stack.
+            if depth <= tallest {Some((self.first_column() + x as u8 - 1, Some(depth)))} else {None}
+        } else {
+            None
+        }
+    }
+
+    /// Replace the board with the result of the player's move, and its automoves.
+    fn make_move(&mut self, mv: Move, board: Board) {
+        self.history.push((mv, mem::replace(&mut self.board, board.do_automoves_with(self.automoves))));
+        self.future.clear();
+    }
+
+    fn undo(&mut self) {
+        match self.history.pop() {
+            Some((mv, board)) => {
+                self.future.push((mv, mem::replace(&mut self.board, board)));
+                self.mode = GameMode::SelectSource;
+            },
+            None => self.message = Some(String::from("Nothing to undo.")),
+        }
+    }
+
+    fn redo(&mut self) {
+        match self.future.pop() {
+            Some((mv, board)) => {
+                self.history.push((mv, mem::replace(&mut self.board, board)));
+                self.mode = GameMode::SelectSource;
+            },
+            None => self.message = Some(String::from("Nothing to redo.")),
+        }
+    }
+
     fn cancel(&mut self) {
         self.mode = match self.mode {
             GameMode::SelectDestination{..} | GameMode::SelectSource => GameMode::SelectSource,
-            GameMode::ChooseStackHeight{cursor, ..} => GameMode::SelectDestination{cursor},
+            GameMode::ChooseStackHeight{cursor, ..} => GameMode::SelectDestination{cursor, height: None},
         }
     }
 
-    /// Immediately move the cursor to the given value in the current row.
-    ///
-    /// `num` MUST be in the range 1...8
+    /// Immediately move the cursor to the given value in the current row, counting from 1. Does
+    /// nothing if the row isn't that long.
     fn jump_to(&mut self, num: u8) {
-        if self.cursor >= 7 {
-            self.cursor = num + 6;
+        if num < 1 {
+            return;
+        }
+        if self.is_column(self.cursor) {
+            if num as usize <= self.board.rules().columns {
+                self.cursor = self.first_column() + num - 1;
+            }
         }
-        else if num <= 6 {
+        else if num < self.first_column() {
             self.cursor = num;
         }
     }
 
-    fn cursor_to_cci(cursor: u8) -> CardCellIndex {
-        match cursor {
-            1...3 => CardCellIndex::FreeCellIndex(cursor as usize - 1),
-            4...6 => CardCellIndex::GoalCellIndex(cursor as usize - 4),
-            7...14 => CardCellIndex::GameCellIndex(cursor as usize - 7),
-            _ => panic!(format!("Invalid cursor value {}", cursor)),
+    /// Immediately move the cursor to the last cell in the current row.
+    fn jump_to_end(&mut self) {
+        let end = if self.is_column(self.cursor) {self.board.rules().columns as u8}
+            else {self.first_column() - 1};
+        self.jump_to(end);
+    }
+
+    fn cursor_to_cci(&self, cursor: u8) -> CardCellIndex {
+        let rules = self.board.rules();
+        let n = cursor as usize;
+        match n {
+            _ if 1 <= n && n <= rules.free_cells => CardCellIndex::FreeCellIndex(n - 1),
+            _ if self.is_goal(cursor) => CardCellIndex::GoalCellIndex(n - rules.free_cells - 1),
+            _ if self.is_column(cursor) && cursor <= self.last_column() =>
+                CardCellIndex::GameCellIndex(n - rules.free_cells - rules.suits - 1),
+            _ => panic!("Invalid cursor value {}", cursor),
+        }
+    }
+
+    fn cci_to_cursor(&self, index: &CardCellIndex) -> u8 {
+        let rules = self.board.rules();
+        match *index {
+            CardCellIndex::FreeCellIndex(n) => n as u8 + 1,
+            CardCellIndex::GoalCellIndex(n) => (n + rules.free_cells) as u8 + 1,
+            CardCellIndex::GameCellIndex(n) => n as u8 + self.first_column(),
         }
     }
 
+    /// Look for a solution from here, and suggest its first move.
+    fn hint(&mut self) {
+        let config = SolverConfig{
+            max_time: Some(HINT_MAX_TIME),
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            automoves: self.automoves,
+            ..SolverConfig::default()
+        };
+        let outcome = solver::solve(&self.board, &config).map(|report| report.outcome);
+        self.message = Some(match outcome {
+            Ok(SolveOutcome::Solved(solution)) => match solution.moves.first() {
+                Some(mv) => {
+                    self.hint = Some(*mv);
+                    format!("Hint: {}.", display_move(&self.board, mv))
+                },
+                None => String::from("Nothing left to do."),
+            },
+            Ok(SolveOutcome::ProvenUnsolvable) =>
+                String::from("There's no way to win from here. Sorry!"),
+            Ok(SolveOutcome::BudgetExhausted{..}) =>
+                String::from("Couldn't find a way to win from here in time."),
+            Err(_) => String::from("This board couldn't come from a real game."),
+        });
+    }
+
     fn stack_dragons(&mut self) {
         match self.cell_at(self.cursor).top() {
             Some(rc_card) => match &*rc_card {
                 &Card::DragonCard{suit} => {
                     match self.board.stack_dragons(suit) {
-                        Some(board) => {
-                            self.board = board.do_automoves();
-                        },
+                        Some(board) => self.make_move(Move::GroupDragons{suit}, board),
                         None => (),
                     }
                 },
@@ -260,12 +612,8 @@ impl Game {
                     }
                 },
             GameMode::SelectSource | GameMode::SelectDestination{..} =>
-                self.cursor = match self.cursor {
-                    7 => 2,
-                    8...10 => 3,
-                    11...13 => 4,
-                    14 => 5,
-                    _ => return,
+                if self.is_column(self.cursor) {
+                    self.cursor = self.nearest(self.cursor, 1..self.first_column());
                 },
         }
     }
@@ -273,9 +621,8 @@ impl Game {
     fn move_cursor_left(&mut self) {
         match self.mode {
             GameMode::SelectSource | GameMode::SelectDestination{..} =>
-                self.cursor = match self.cursor {
-                    1 | 7 => return,
-                    _ => self.cursor - 1
+                if self.cursor != 1 && self.cursor != self.first_column() {
+                    self.cursor -= 1;
                 },
             GameMode::ChooseStackHeight{..} => (),
         }
@@ -284,9 +631,8 @@ impl Game {
     fn move_cursor_right(&mut self) {
         match self.mode {
             GameMode::SelectSource | GameMode::SelectDestination{..} =>
-                self.cursor = match self.cursor {
-                    6 | 14 => return,
-                    _ => self.cursor + 1
+                if self.cursor + 1 != self.first_column() && self.cursor != self.last_column() {
+                    self.cursor += 1;
                 },
             GameMode::ChooseStackHeight{..} => (),
         }
@@ -303,34 +649,217 @@ impl Game {
                     }
                 },
             GameMode::SelectSource | GameMode::SelectDestination{..} =>
-                self.cursor = match self.cursor {
-                    1 => 7,
-                    2 | 3 => self.cursor + 5,
-                    4 | 5 => self.cursor + 9,
-                    6 => 14,
-                    _ => return,
+                if !self.is_column(self.cursor) {
+                    self.cursor = self.nearest(self.cursor, self.first_column()..=self.last_column());
                 },
         }
     }
 
-    pub fn print_controls() {
-        println!("{}", indoc!("
+    fn controls() -> &'static str {
+        indoc!("
             Controls:
-            - WASD to move the cursor (until I can figure out how to support the arrow keys)
-            - Space to select or place a card
+            - WASD or the arrow keys to move the cursor
+            - Space or Enter to select or place a card
             - G to group the selected dragons
-            - C to cancel a selection
-            - 1-8 to jump within the current row
-            - ? to show these controls
-        "));
+            - H for a hint
+            - U to undo a move, and R to redo it
+            - V to save the game, and Q or Ctrl-C to save and quit
+            - C or Escape to cancel a selection
+            - 1-9 to jump within the current row, or Home and End to jump to either end of it
+            - Click a card to select it and the cards on it, then click where to place them
+            - Double-click a dragon to group it
+            - ? to show or hide these controls
+        ")
+    }
+
+    fn cell_at(&self, cursor: u8) -> &Arc<CardCell> {
+        self.board.get_cell(&self.cursor_to_cci(cursor))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+    use ::notation;
+
+    #[test]
+    /// Ensure undo rolls back a move along with its automoves, and redo brings them back.
+    fn undo_redo() {
+        let seed = Seed::from_string("8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&").unwrap();
+        let start = Board::deal_seeded(&seed).do_automoves();
+        let mut game = Game::with_rules(seed, &RuleSet::default());
+        game.board = start.clone();
+        game.undo();
+        assert!(game.board == start);
+
+        // Move a card from the last column to a free cell.
+        game.cursor = 14;
+        game.select();
+        game.cursor = 1;
+        game.select();
+        let moved = game.board.clone();
+        assert!(moved != start);
+
+        game.undo();
+        assert!(game.board == start);
+        game.redo();
+        assert!(game.board == moved);
+        game.redo();
+        assert!(game.board == moved);
+
+        // A new move forgets anything undone.
+        game.undo();
+        game.cursor = 13;
+        game.select();
+        game.cursor = 1;
+        game.select();
+        assert!(game.future.is_empty());
+        game.undo();
+        assert!(game.board == start);
     }
 
-    fn cell_at(&self, cursor: u8) -> &Rc<CardCell> {
-        match cursor {
-            1...3 => &self.board.free_cells()[cursor as usize - 1],
-            4...6 => &self.board.goal_cells()[cursor as usize - 4],
-            7...14 => &self.board.game_cells()[cursor as usize - 7],
-            other => panic!(format!("unexpected cursor value {}", other)),
+    #[test]
+    /// Ensure a resumed game picks up exactly where it was saved, history and all.
+    fn resume() {
+        let seed = Seed::from_string("8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&").unwrap();
+        let mut game = Game::with_rules(seed, &RuleSet::default());
+        game.board = game.board.do_automoves();
+        let start = game.board.clone();
+        for &(source, dest) in &[(14, 1), (13, 2), (12, 3)] {
+            game.cursor = source;
+            game.select();
+            game.cursor = dest;
+            game.select();
         }
+        game.undo();
+        game.cursor = 9;
+
+        let text = save::format_saved_game(&game.to_saved());
+        let mut resumed = Game::resume(save::parse_saved_game(&text).expect("should load"));
+        assert!(resumed.board == game.board);
+        assert_eq!(resumed.cursor, 9);
+        assert!(resumed.elapsed() >= game.elapsed_before);
+        resumed.redo();
+        assert_eq!(resumed.history.len(), 3);
+        resumed.undo();
+        resumed.undo();
+        resumed.undo();
+        assert!(resumed.board == start);
+    }
+
+    #[test]
+    /// Ensure a won game is saved as won, rather than left at the last position saved before.
+    fn save_when_won() {
+        let seed = Seed::from_string("8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&").unwrap();
+        let mut game = Game::with_rules(seed, &RuleSet::default());
+        game.board = game.board.do_automoves();
+        let solution = match solver::solve(&game.board, &SolverConfig::default()).unwrap().outcome {
+            SolveOutcome::Solved(solution) => solution,
+            _ => panic!("expected the deal to be solved"),
+        };
+        let path = env::temp_dir().join(format!("shenzhen-solitaire-won-{}.save", process::id()));
+        game.save_to(path.to_string_lossy().into_owned());
+        game.save();
+        for mv in solution.moves {
+            let board = game.board.apply_move(&mv).expect("the solution's moves are legal");
+            game.make_move(mv, board);
+        }
+        assert!(game.board.is_solved());
+        game.finish();
+
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let resumed = Game::resume(save::parse_saved_game(&text).expect("should load"));
+        assert!(resumed.board.is_solved());
+    }
+
+    #[test]
+    /// Ensure the cursor finds its way around boards of other rule sets.
+    fn cursor_with_rules() {
+        let seed = Seed::from_string("8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&").unwrap();
+        let mut game = Game::with_rules(seed, &RuleSet::from_string("free=4,columns=9,suits=4").unwrap());
+        // The free cells are 1-4, the goal cells 5-8 and the game cells 9-17.
+        assert_eq!(game.cursor, 13);
+        assert!(game.cursor_to_cci(4) == CardCellIndex::FreeCellIndex(3));
+        assert!(game.cursor_to_cci(8) == CardCellIndex::GoalCellIndex(3));
+        assert!(game.cursor_to_cci(17) == CardCellIndex::GameCellIndex(8));
+        assert_eq!(game.cci_to_cursor(&CardCellIndex::GoalCellIndex(0)), 5);
+        assert_eq!(game.cursor_at(9, 1), Some((5, None)));
+        assert_eq!(game.cursor_at(10, 3), Some((17, Some(0))));
+
+        game.jump_to_end();
+        assert_eq!(game.cursor, 17);
+        game.move_cursor_right();
+        assert_eq!(game.cursor, 17);
+        // The last column is right below the second goal cell.
+        game.move_cursor_up();
+        assert_eq!(game.cursor, 6);
+        game.jump_to_end();
+        assert_eq!(game.cursor, 8);
+        game.move_cursor_right();
+        assert_eq!(game.cursor, 8);
+        game.jump_to(1);
+        assert_eq!(game.cursor, 1);
+        game.move_cursor_down();
+        assert_eq!(game.cursor, 9);
+        game.move_cursor_left();
+        assert_eq!(game.cursor, 9);
+        game.jump_to(9);
+        assert_eq!(game.cursor, 17);
+    }
+
+    #[test]
+    /// Ensure clicks land on the cells drawn under them, and select and place cards.
+    fn click() {
+        let seed = Seed::from_string("8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&").unwrap();
+        let mut game = Game::with_rules(seed, &RuleSet::default());
+        game.board = notation::parse_board(indoc!("
+            free: GD - -
+            joker: J
+            goal: - - -
+            1: R9 R5 B4 R3
+            2: G5
+            3: GD
+            4: GD
+            5: -
+            6: -
+            7: -
+            8: GD
+        ")).unwrap();
+
+        assert_eq!(game.cursor_at(1, 1), Some((1, None)));
+        assert_eq!(game.cursor_at(3, 2), Some((3, None)));
+        assert_eq!(game.cursor_at(8, 1), Some((4, None)));
+        assert_eq!(game.cursor_at(6, 2), None);
+        assert_eq!(game.cursor_at(2, 3), Some((7, Some(0))));
+        assert_eq!(game.cursor_at(2, 6), Some((7, Some(3))));
+        assert_eq!(game.cursor_at(9, 7), Some((14, Some(4))));
+        assert_eq!(game.cursor_at(9, 8), None);
+        assert_eq!(game.cursor_at(10, 3), None);
+
+        // Move B4 and R3 onto G5.
+        let now = Instant::now();
+        game.click(2, 5, now);
+        game.click(3, 3, now + Duration::from_secs(1));
+        assert_eq!(game.board.game_cells()[0].len(), 2);
+        assert_eq!(game.board.game_cells()[1].len(), 3);
+        assert_eq!(game.history.len(), 1);
+
+        // Clicking a selection again lets go of it.
+        game.click(3, 5, now + Duration::from_secs(2));
+        game.click(3, 4, now + Duration::from_secs(3));
+        match game.mode {
+            GameMode::SelectSource => (),
+            _ => panic!("expected nothing to be selected"),
+        }
+
+        // Group the green dragons.
+        game.click(1, 2, now + Duration::from_secs(4));
+        game.click(1, 2, now + Duration::from_secs(4));
+        assert_eq!(game.history.len(), 2);
+        assert!(game.board.free_cells().iter().any(|cell|
+            cell.top().is_some_and(|card| *card == Card::DragonStack)));
     }
 }
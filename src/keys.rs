@@ -0,0 +1,198 @@
+//! Reading keys from the terminal, including the ones which arrive as escape sequences.
+
+extern crate getch;
+#[cfg(unix)]
+extern crate termios;
+
+use std::io::{self, Read};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+/// How long to wait after an escape byte for the rest of an escape sequence. Terminals send the
+/// whole sequence at once, so anything slower than this is the Escape key on its own.
+const ESCAPE_TIMEOUT: Duration = Duration::from_millis(50);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Key {
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    Enter,
+    Escape,
+    /// Ctrl-C, which arrives as a key rather than killing the program.
+    Interrupt,
+    /// A left click at the given column and row of the terminal, counting from 1.
+    Click{x: u16, y: u16},
+    /// An escape sequence we don't know what to do with.
+    Unknown,
+}
+
+/// Keys typed at the terminal.
+pub struct Keys {
+    bytes: Receiver<io::Result<u8>>,
+    /// Keeps the terminal from echoing or buffering input until dropped.
+    _getch: getch::Getch,
+}
+
+impl Keys {
+    pub fn new() -> Keys {
+        let getch = getch::Getch::new();
+        interrupt_as_key();
+        // Read on another thread, so we can stop waiting for the rest of an escape sequence.
+        let (sender, bytes) = mpsc::channel();
+        thread::spawn(move || {
+            let stdin = io::stdin();
+            let mut stdin = stdin.lock();
+            let mut byte = [0];
+            loop {
+                let result = match stdin.read(&mut byte) {
+                    Ok(0) => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "end of input")),
+                    Ok(_) => Ok(byte[0]),
+                    Err(err) => Err(err),
+                };
+                let done = result.is_err();
+                if sender.send(result).is_err() || done {
+                    return;
+                }
+            }
+        });
+        Keys{bytes, _getch: getch}
+    }
+
+    /// Wait for the next key.
+    pub fn next(&mut self) -> io::Result<Key> {
+        let bytes = &self.bytes;
+        let first = bytes.recv().unwrap_or_else(|_| Err(io::ErrorKind::UnexpectedEof.into()))?;
+        let mut error = None;
+        let key = decode(first, &mut || match bytes.recv_timeout(ESCAPE_TIMEOUT) {
+            Ok(Ok(byte)) => Some(byte),
+            Ok(Err(err)) => {
+                error = Some(err);
+                None
+            },
+            Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => None,
+        });
+        match error {
+            Some(err) => Err(err),
+            None => Ok(key),
+        }
+    }
+}
+
+/// Stop Ctrl-C from killing the program, so it arrives as a key instead and quitting puts the
+/// terminal back as it was. Dropping the `Getch` undoes this along with the rest.
+#[cfg(unix)]
+fn interrupt_as_key() {
+    if let Ok(mut settings) = termios::Termios::from_fd(0) {
+        settings.c_lflag &= !termios::ISIG;
+        let _ = termios::tcsetattr(0, termios::TCSADRAIN, &settings);
+    }
+}
+
+#[cfg(not(unix))]
+fn interrupt_as_key() {}
+
+/// Decode the key starting with the byte `first`. `next` returns the byte after, or None if
+/// there's no byte after yet.
+fn decode<F: FnMut() -> Option<u8>>(first: u8, next: &mut F) -> Key {
+    match first {
+        b'\x1b' => (),
+        b'\r' | b'\n' => return Key::Enter,
+        b'\x03' => return Key::Interrupt,
+        byte => return Key::Char(byte as char),
+    }
+    match next() {
+        None => Key::Escape,
+        // "Control sequence introducer": parameter bytes, then a final byte saying what it is.
+        Some(b'[') => {
+            let mut parameters = Vec::new();
+            loop {
+                match next() {
+                    Some(byte @ 0x40..=0x7e) => return decode_csi(&parameters, byte),
+                    Some(byte) => parameters.push(byte),
+                    None => return Key::Unknown,
+                }
+            }
+        },
+        // Some terminals send these for the arrow keys, Home and End instead.
+        Some(b'O') => match next() {
+            Some(byte) => decode_csi(&[], byte),
+            None => Key::Unknown,
+        },
+        Some(_) => Key::Unknown,
+    }
+}
+
+fn decode_csi(parameters: &[u8], last: u8) -> Key {
+    match (parameters, last) {
+        (_, b'A') => Key::Up,
+        (_, b'B') => Key::Down,
+        (_, b'C') => Key::Right,
+        (_, b'D') => Key::Left,
+        (_, b'H') | (b"1", b'~') | (b"7", b'~') => Key::Home,
+        (_, b'F') | (b"4", b'~') | (b"8", b'~') => Key::End,
+        // Mouse presses, in SGR mode: "<button;x;y".
+        ([b'<', rest @ ..], b'M') => decode_mouse(rest),
+        _ => Key::Unknown,
+    }
+}
+
+fn decode_mouse(parameters: &[u8]) -> Key {
+    let numbers: Vec<u16> = match String::from_utf8_lossy(parameters).split(';')
+            .map(|number| number.parse()).collect() {
+        Ok(numbers) => numbers,
+        Err(_) => return Key::Unknown,
+    };
+    match numbers[..] {
+        // Anything but the left button, or with modifiers, motion or the wheel, is ignored.
+        [0, x, y] => Key::Click{x, y},
+        _ => Key::Unknown,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(bytes: &[u8]) -> Vec<Key> {
+        let mut bytes = bytes.iter().cloned();
+        let mut keys = Vec::new();
+        while let Some(first) = bytes.next() {
+            keys.push(decode(first, &mut || bytes.next()));
+        }
+        keys
+    }
+
+    #[test]
+    fn decode_keys() {
+        assert_eq!(
+            decode_all(b"w \r\x03\x1b[A\x1b[B\x1b[C\x1b[D\x1bOA\x1b[H\x1b[4~\x1b[1;5A\x1b[Z\x1b[<0;12;5M\x1b[<0;12;5m\x1b[<2;1;1M\x1b[<32;3;4M\x1b"),
+            vec![
+                Key::Char('w'),
+                Key::Char(' '),
+                Key::Enter,
+                Key::Interrupt,
+                Key::Up,
+                Key::Down,
+                Key::Right,
+                Key::Left,
+                Key::Up,
+                Key::Home,
+                Key::End,
+                Key::Up,
+                Key::Unknown,
+                Key::Click{x: 12, y: 5},
+                Key::Unknown,
+                Key::Unknown,
+                Key::Unknown,
+                Key::Escape,
+            ],
+        );
+    }
+}
@@ -0,0 +1,56 @@
+//! Shenzhen IO's solitaire minigame: dealing boards, making moves on them, and solving them.
+//!
+//! A `Board` is dealt from a `Seed`, moves are made on it with `Board::apply_move`, and
+//! `solve` looks for a way to win from it:
+//!
+//! ```
+//! extern crate shenzhen_solitaire_solver;
+//!
+//! use shenzhen_solitaire_solver::{solve, Board, CardCellIndex, Move, Seed, SolveOutcome, SolverConfig};
+//!
+//! # fn main() {
+//! let seed = Seed::from_string("8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&").unwrap();
+//! // The real game makes some moves on its own as soon as the cards are dealt.
+//! let board = Board::deal_seeded(&seed).do_automoves();
+//!
+//! let mv = Move::MoveStack{
+//!     source: CardCellIndex::GameCellIndex(7),
+//!     dest: CardCellIndex::FreeCellIndex(0),
+//!     height: 1,
+//! };
+//! let next = board.apply_move(&mv).expect("the move is legal").do_automoves();
+//! assert!(next.validate().is_empty());
+//!
+//! let config = SolverConfig{max_nodes: Some(1000), ..SolverConfig::default()};
+//! match solve(&board, &config).expect("dealt boards are valid").outcome {
+//!     SolveOutcome::Solved(solution) => println!("won in {} moves", solution.moves.len()),
+//!     SolveOutcome::ProvenUnsolvable => println!("can't be won"),
+//!     SolveOutcome::BudgetExhausted{..} => println!("gave up"),
+//! }
+//! # }
+//! ```
+//!
+//! Boards can also be read and written as text with `notation`, deals rated with `rating`, and
+//! rule sets other than the real game's described with `RuleSet`.
+
+#![warn(missing_docs)]
+
+#[cfg(test)]
+#[macro_use]
+extern crate indoc;
+
+pub mod board;
+pub mod notation;
+mod packed;
+pub mod rating;
+pub mod solver;
+mod zobrist;
+
+pub use board::{
+    AutoMovePolicy, Board, BoardError, Card, CardCell, CardCellIndex, Move, MoveStackError, RuleSet,
+    RuleSetError, Seed, SeedError, Suit, Violation,
+};
+pub use solver::{
+    deal_solvable, solve, solve_optimal, SolvableDeal, Solution, SolveError, SolveOutcome, SolveReport,
+    SolverConfig, SolverStats,
+};
@@ -1,50 +1,428 @@
 #[macro_use]
 extern crate indoc;
+extern crate shenzhen_solitaire_solver;
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use shenzhen_solitaire_solver::{board, notation, rating, solver};
 
 #[macro_use]
 mod display;
+mod batch;
 mod game;
-mod board;
+mod keys;
+mod save;
+mod screen;
 mod util;
-mod solver;
 
 
 fn print_usage(exe: &str) {
-    println!("usage: {} {{play,solve}} [seed]", exe);
+    println!("usage: {} {{play,solve,batch,show,rate}} [seed]", exe);
+    println!("       {} play [[seed | --solvable] [--automoves POLICY] [--rules RULES] | --resume FILE] [--save FILE]", exe);
+    println!("             [--max-nodes N] [--max-time SECONDS] [--max-memory MB] [--threads N]");
+    println!("       {} solve [seed] [--board FILE] [--optimal] [--stats] [--automoves POLICY] [--rules RULES]", exe);
+    println!("             [--max-nodes N] [--max-time SECONDS] [--max-memory MB] [--threads N]");
+    println!("       {} batch [--deals N | --seeds FILE] [--csv FILE] [--json FILE] [--automoves POLICY]", exe);
+    println!("             [--rules RULES] [--max-nodes N] [--max-time SECONDS] [--max-memory MB] [--threads N]");
+    println!("       {} rate [seed] [--board FILE] [--automoves POLICY] [--rules RULES]", exe);
+    println!("             [--max-nodes N] [--max-time SECONDS] [--max-memory MB] [--threads N]");
+    println!("       {} show [seed] [--rules RULES]", exe);
+    println!("where POLICY is one of game (the default), conservative or none");
+    println!("and RULES is eg free=4,columns=9,suits=4,ranks=9,dragons=4,joker=no");
+}
+
+/// Where `play` saves the game, unless told otherwise.
+const DEFAULT_SAVE_PATH: &str = "shenzhen-solitaire.save";
+/// How many deals `batch` solves, unless told otherwise.
+const DEFAULT_BATCH_DEALS: usize = 100;
+/// How many nodes `batch` may expand per deal, unless given some other limit.
+const DEFAULT_BATCH_MAX_NODES: u64 = 100_000;
+/// How many nodes `play --solvable` may expand per deal it tries, unless given some other limit.
+const DEFAULT_SOLVABLE_MAX_NODES: u64 = 100_000;
+
+/// Command line arguments following the subcommand.
+struct Options {
+    seed: Option<String>,
+    /// Path to a board in text notation, or "-" for stdin.
+    board: Option<String>,
+    /// Find the shortest solution, rather than any solution.
+    optimal: bool,
+    /// Print the solver's statistics once it's done.
+    stats: bool,
+    config: solver::SolverConfig,
+    /// How many deals to solve in a batch.
+    deals: usize,
+    /// Path to a file of seeds to solve in a batch, one per line.
+    seeds: Option<String>,
+    /// Paths to write a batch's results to.
+    csv: Option<String>,
+    json: Option<String>,
+    /// Path to a saved game to pick up.
+    resume: Option<String>,
+    /// Path to save the game to.
+    save: Option<String>,
+    /// Only deal a board the solver can win.
+    solvable: bool,
+    /// Whether `--automoves` was given, since saved games have their own.
+    automoves: bool,
+    /// The rule set to deal and play by, if not the real game's.
+    rules: Option<board::RuleSet>,
+}
+
+impl Options {
+    fn parse(exe: &str, args: &[String]) -> Options {
+        let mut options = Options{
+            seed: None,
+            board: None,
+            optimal: false,
+            stats: false,
+            config: solver::SolverConfig::default(),
+            deals: DEFAULT_BATCH_DEALS,
+            seeds: None,
+            csv: None,
+            json: None,
+            resume: None,
+            save: None,
+            solvable: false,
+            automoves: false,
+            rules: None,
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--board" => options.board = Some(args.next().cloned().unwrap_or_else(|| {
+                    eprintln!("{}: error: --board needs a file name (or - for stdin)", exe);
+                    process::exit(1);
+                })),
+                "--seeds" => options.seeds = Some(Options::parse_path(exe, arg, args.next())),
+                "--csv" => options.csv = Some(Options::parse_path(exe, arg, args.next())),
+                "--json" => options.json = Some(Options::parse_path(exe, arg, args.next())),
+                "--resume" => options.resume = Some(Options::parse_path(exe, arg, args.next())),
+                "--save" => options.save = Some(Options::parse_path(exe, arg, args.next())),
+                "--deals" => options.deals = Options::parse_number(exe, arg, args.next()),
+                "--optimal" => options.optimal = true,
+                "--stats" => options.stats = true,
+                "--solvable" => options.solvable = true,
+                "--max-nodes" =>
+                    options.config.max_nodes = Some(Options::parse_number(exe, arg, args.next())),
+                "--max-time" => options.config.max_time = Some(Duration::from_millis(
+                    (Options::parse_number::<f64>(exe, arg, args.next()) * 1000.0) as u64
+                )),
+                "--max-memory" => options.config.max_memory = Some(
+                    Options::parse_number::<usize>(exe, arg, args.next()) * 1024 * 1024
+                ),
+                "--automoves" => {
+                    options.config.automoves = args.next()
+                        .and_then(|name| board::AutoMovePolicy::from_name(name))
+                        .unwrap_or_else(|| {
+                            eprintln!("{}: error: --automoves needs one of game, conservative or none", exe);
+                            process::exit(1);
+                        });
+                    options.automoves = true;
+                },
+                "--rules" => options.rules = Some(match args.next().map(|rules| board::RuleSet::from_string(rules)) {
+                    Some(Ok(rules)) => rules,
+                    Some(Err(err)) => {
+                        eprintln!("{}: error: --rules: {}", exe, err);
+                        process::exit(1);
+                    },
+                    None => {
+                        eprintln!("{}: error: --rules needs a list of rules, eg free=4,joker=no", exe);
+                        process::exit(1);
+                    },
+                }),
+                "--threads" =>
+                    options.config.threads = Options::parse_number(exe, arg, args.next()),
+                flag if flag.starts_with("--") => {
+                    eprintln!("{}: error: unrecognized option '{}'", exe, flag);
+                    process::exit(1);
+                },
+                seed => options.seed = Some(seed.to_string()),
+            }
+        }
+        options
+    }
+
+    fn parse_path(exe: &str, flag: &str, value: Option<&String>) -> String {
+        value.cloned().unwrap_or_else(|| {
+            eprintln!("{}: error: {} needs a file name", exe, flag);
+            process::exit(1);
+        })
+    }
+
+    fn parse_number<T: FromStr>(exe: &str, flag: &str, value: Option<&String>) -> T {
+        match value.map(|value| value.parse()) {
+            Some(Ok(number)) => number,
+            _ => {
+                eprintln!("{}: error: {} needs a number", exe, flag);
+                process::exit(1);
+            },
+        }
+    }
+}
+
+/// Read a board in the notation described in `notation`, from a file or, given "-", stdin.
+fn read_board(exe: &str, path: &str, rules: &board::RuleSet) -> board::Board {
+    let mut text = String::new();
+    let result = if path == "-" {
+        io::stdin().read_to_string(&mut text)
+    }
+    else {
+        File::open(path).and_then(|mut file| file.read_to_string(&mut text))
+    };
+    if let Err(err) = result {
+        eprintln!("{}: error: could not read board from '{}': {}", exe, path, err);
+        process::exit(1);
+    }
+    match notation::parse_board_with_rules(&text, rules) {
+        Ok(board) => board,
+        Err(err) => {
+            eprintln!("{}: error: could not parse board from '{}': {}", exe, path, err);
+            process::exit(1);
+        }
+    }
+}
+
+/// Overwrite the current line of stderr with how the search is going.
+fn print_progress(stats: &solver::SolverStats) {
+    eprint!(
+        "\r\x1b[Ksearching: {} nodes expanded, peak open set {}, best hscore {}, {}s",
+        stats.nodes_expanded,
+        stats.peak_open,
+        stats.best_hscore.map_or("-".to_string(), |hscore| hscore.to_string()),
+        stats.elapsed.as_secs(),
+    );
+    let _ = io::stderr().flush();
+}
+
+/// Read seeds, one per line, from a file or, given "-", stdin.
+fn read_seeds(exe: &str, path: &str) -> Vec<board::Seed> {
+    let mut text = String::new();
+    let result = if path == "-" {
+        io::stdin().read_to_string(&mut text)
+    }
+    else {
+        File::open(path).and_then(|mut file| file.read_to_string(&mut text))
+    };
+    if let Err(err) = result {
+        eprintln!("{}: error: could not read seeds from '{}': {}", exe, path, err);
+        process::exit(1);
+    }
+    text.lines().map(|line| line.trim()).filter(|line| !line.is_empty()).map(|line|
+        board::Seed::from_string(line).unwrap_or_else(|err| {
+            eprintln!("{}: error: invalid seed '{}' in '{}': {}", exe, line, path, err);
+            process::exit(1);
+        })
+    ).collect()
+}
+
+/// Write a batch's results to `path`, with `write`.
+fn write_results<F>(exe: &str, path: &str, results: &[batch::DealResult], write: F) where
+    F: FnOnce(&[batch::DealResult], File) -> io::Result<()>,
+{
+    if let Err(err) = File::create(path).and_then(|file| write(results, file)) {
+        eprintln!("{}: error: could not write results to '{}': {}", exe, path, err);
+        process::exit(1);
+    }
+}
+
+fn print_moves(solution: &solver::Solution) {
+    for (i, (mv, board)) in solution.moves.iter().zip(solution.boards.iter()).enumerate() {
+        println!("{:>3}. {}", i + 1, display::display_move(board, mv));
+    }
 }
 
 fn main() {
     let exe = std::env::args().nth(0).expect("Could not find executable name");
+    let args: Vec<String> = std::env::args().skip(2).collect();
+    let mut options = Options::parse(&exe, &args);
+    let rules = options.rules.clone().unwrap_or_default();
 
-    let (b, seed) = match std::env::args().nth(2) {
-        Some(seed_str) => {
-            let seed = board::Seed::from_string(&seed_str);
-            (board::Board::deal_seeded(&seed), seed)
-        },
-        None => board::Board::deal()
+    let deal = || {
+        let seed = match options.seed {
+            Some(ref seed_str) => board::Seed::from_string(&seed_str).unwrap_or_else(|err| {
+                eprintln!("{}: error: invalid seed '{}': {}", exe, seed_str, err);
+                process::exit(1);
+            }),
+            None => board::Seed::random(),
+        };
+        (board::Board::deal_with_rules(&seed, &rules), seed)
     };
 
     match std::env::args().nth(1).as_ref().map(|cmd| cmd.as_str()) {
         Some("play") => {
-            println!("{}\n", seed);
-            game::Game::print_controls();
-            game::Game::new(b).play();
+            let mut game = match options.resume {
+                Some(_) if options.automoves => {
+                    eprintln!("{}: error: a resumed game keeps the automoves it was started with", exe);
+                    process::exit(1);
+                },
+                Some(_) if options.rules.is_some() => {
+                    eprintln!("{}: error: a resumed game keeps the rules it was started with", exe);
+                    process::exit(1);
+                },
+                Some(_) if options.solvable => {
+                    eprintln!("{}: error: a resumed game has already been dealt", exe);
+                    process::exit(1);
+                },
+                Some(ref path) => {
+                    let mut text = String::new();
+                    if let Err(err) = File::open(path).and_then(|mut file| file.read_to_string(&mut text)) {
+                        eprintln!("{}: error: could not read saved game from '{}': {}", exe, path, err);
+                        process::exit(1);
+                    }
+                    let saved = save::parse_saved_game(&text).unwrap_or_else(|err| {
+                        eprintln!("{}: error: could not load saved game from '{}': {}", exe, path, err);
+                        process::exit(1);
+                    });
+                    println!("{}\n", saved.seed);
+                    game::Game::resume(saved)
+                },
+                None if options.solvable => {
+                    if options.seed.is_some() {
+                        eprintln!("{}: error: --solvable deals its own seed", exe);
+                        process::exit(1);
+                    }
+                    let config = &mut options.config;
+                    if config.max_nodes.is_none() && config.max_time.is_none() && config.max_memory.is_none() {
+                        config.max_nodes = Some(DEFAULT_SOLVABLE_MAX_NODES);
+                    }
+                    let deal = solver::deal_solvable(&rules, config, None)
+                        .expect("only gives up when told how many deals to try");
+                    println!("{}\n", deal.seed);
+                    if deal.deals > 1 {
+                        println!("(dealt {} boards to find one that can be won)\n", deal.deals);
+                    }
+                    let mut game = game::Game::with_rules(deal.seed, &rules);
+                    game.set_automoves(config.automoves);
+                    game
+                },
+                None => {
+                    let (_, seed) = deal();
+                    println!("{}\n", seed);
+                    let mut game = game::Game::with_rules(seed, &rules);
+                    game.set_automoves(options.config.automoves);
+                    game
+                },
+            };
+            let save_path = options.save.as_ref().or(options.resume.as_ref()).cloned();
+            game.save_to(save_path.unwrap_or_else(|| DEFAULT_SAVE_PATH.to_string()));
+            game.play();
         }
         Some("solve") => {
-            println!("{}", seed);
+            let b = if let Some(ref path) = options.board {
+                read_board(&exe, path, &rules)
+            }
+            else {
+                let (b, seed) = deal();
+                println!("{}", seed);
+                b
+            };
             println!("{}", display::display_board(&b));
-            let b2 = b.do_automoves();
+            let b2 = b.do_automoves_with(options.config.automoves);
+
+            options.config.progress = Some(Arc::new(print_progress));
+            let result = if options.optimal {
+                solver::solve_optimal(&b2, &options.config)
+            }
+            else {
+                solver::solve(&b2, &options.config)
+            };
+            // Clear the progress line.
+            eprint!("\r\x1b[K");
+            let report = result.unwrap_or_else(|solver::SolveError::InvalidBoard(violations)| {
+                eprintln!("{}: error: this board could not come from a real game:", exe);
+                for violation in violations {
+                    eprintln!("  - {}", violation);
+                }
+                process::exit(1);
+            });
+            let solved = match report.outcome {
+                solver::SolveOutcome::Solved(solution) => {
+                    print_moves(&solution);
+                    true
+                },
+                solver::SolveOutcome::ProvenUnsolvable => {
+                    println!("No solution exists for this board.");
+                    false
+                },
+                solver::SolveOutcome::BudgetExhausted{best_partial} => {
+                    print_moves(&best_partial);
+                    println!("Gave up before finding a solution. The moves above get closest.");
+                    false
+                },
+            };
+            if options.stats {
+                println!("\n{}", report.stats);
+            }
+            if !solved {
+                process::exit(1);
+            }
+        }
+        Some("batch") => {
+            let config = &mut options.config;
+            if config.max_nodes.is_none() && config.max_time.is_none() && config.max_memory.is_none() {
+                config.max_nodes = Some(DEFAULT_BATCH_MAX_NODES);
+            }
+            let seeds = match options.seeds {
+                Some(ref path) => read_seeds(&exe, path),
+                None => (0..options.deals).map(|_| board::Seed::random()).collect(),
+            };
+
+            let start = Instant::now();
+            let results: Vec<batch::DealResult> = seeds.iter().map(|seed| {
+                let result = batch::solve_deal(seed, &rules, &options.config);
+                println!("{}", result);
+                result
+            }).collect();
+            println!("\n{}", batch::Summary::new(&results, start.elapsed()));
 
-            let states = solver::solve(&b2).expect("no answer");
-            for board in states {
-                println!("{}", display::display_board(&board));
+            if let Some(ref path) = options.csv {
+                write_results(&exe, path, &results, batch::write_csv);
             }
+            if let Some(ref path) = options.json {
+                write_results(&exe, path, &results, batch::write_json);
+            }
+        }
+        Some("rate") => {
+            let b = if let Some(ref path) = options.board {
+                read_board(&exe, path, &rules)
+            }
+            else {
+                let (b, seed) = deal();
+                println!("{}", seed);
+                b
+            };
+            let config = &mut options.config;
+            if config.max_nodes.is_none() && config.max_time.is_none() && config.max_memory.is_none() {
+                config.max_nodes = Some(rating::DEFAULT_MAX_NODES);
+            }
+            let result = rating::difficulty_with(&b.do_automoves_with(config.automoves), config);
+            match result {
+                Ok(difficulty) => println!("{}", difficulty),
+                Err(solver::SolveError::InvalidBoard(violations)) => {
+                    eprintln!("{}: error: this board could not come from a real game:", exe);
+                    for violation in violations {
+                        eprintln!("  - {}", violation);
+                    }
+                    process::exit(1);
+                },
+            }
+        }
+        Some("show") => {
+            let (b, seed) = deal();
+            println!("# {}", seed);
+            print!("{}", notation::format_board(&b));
         }
         None => print_usage(&exe),
         Some(cmd) => {
             print_usage(&exe);
             println!(
-                "{}: error: argument cmd: invalid choice: '{}' (choose from 'play', 'solve')",
+                "{}: error: argument cmd: invalid choice: '{}' (choose from 'play', 'solve', 'show', 'batch', 'rate')",
                 &exe, cmd,
             );
         }
@@ -0,0 +1,485 @@
+//! A plain-text board notation, so real deals can be typed in by hand.
+//!
+//! A board is written one cell group per line, each line a label followed by a colon and a
+//! whitespace-separated list of cards:
+//!
+//! ```text
+//! # Blank lines and lines starting with '#' are ignored.
+//! free: GD - X
+//! joker: -
+//! goal: R2 - -
+//! 1: R9 GD B5 G7 B3
+//! 2: G1 B2 R8 RD -
+//! ...
+//! 8: BD B9 G3 G2
+//! ```
+//!
+//! - `free` lists the three free cells, left to right.
+//! - `joker` is the joker cell: `J` if the joker has been placed there, `-` otherwise.
+//! - `goal` lists the top card of each of the three goal cells, left to right.
+//! - `1` through `8` list the cards in each column from the bottom (covered) card to the top
+//!   (exposed) card. An empty column may be left blank or written as a lone `-`.
+//!
+//! Boards played by other rules (see `RuleSet`) list as many free cells, goal cells and columns
+//! as those rules have. If they deal no joker, the `joker` line may be left out.
+//!
+//! Cards are written as:
+//!
+//! - `B`, `G`, `R` or `Y` (black, green, red or yellow) followed by a rank for number cards, eg
+//!   `G7`.
+//! - `BD`, `GD`, `RD` or `YD` for dragons.
+//! - `J` for the joker.
+//! - `X` for a grouped stack of dragons, which may only appear in a free cell.
+//! - `-` for an empty cell.
+//!
+//! Labels and cards are case-insensitive.
+//!
+//! Moves are written as the source cell, the destination cell and how many cards to move, eg
+//! `c3 f1 1`, or as `group` and a suit letter, eg `group G`. Cells are written `f1` through `f3`
+//! for free cells, `g1` through `g3` for goal cells and `c1` through `c8` for columns.
+use std::fmt;
+
+use ::board::{Board, Card, CardCell, CardCellIndex, Move, RuleSet, Suit};
+
+/// No rule set has more than this many of any kind of cell.
+const MAX_CELLS: usize = 9;
+
+/// Why `parse_board` refused some text. Lines are counted from 1.
+#[derive(Debug, Eq, PartialEq)]
+pub enum ParseError {
+    /// A line that is neither blank, a comment, nor a `label: cards` pair.
+    MalformedLine{
+        /// The line.
+        line: usize,
+    },
+    /// A label other than `free`, `joker`, `goal` or a column number.
+    UnknownLabel{
+        /// The line it's on.
+        line: usize,
+        /// The label.
+        label: String,
+    },
+    /// The same label appears on more than one line.
+    DuplicateLabel{
+        /// The line it appears on again.
+        line: usize,
+        /// The label.
+        label: String,
+    },
+    /// A required label appears on no line.
+    MissingLabel{
+        /// The label.
+        label: String,
+    },
+    /// A token that doesn't name a card.
+    InvalidCard{
+        /// The line it's on.
+        line: usize,
+        /// The token.
+        token: String,
+    },
+    /// A card that can't be in this kind of cell, eg a dragon in a goal cell.
+    MisplacedCard{
+        /// The line it's on.
+        line: usize,
+        /// The token naming the card.
+        token: String,
+    },
+    /// A line listing the wrong number of cells.
+    WrongCellCount{
+        /// The line.
+        line: usize,
+        /// How many cells of that kind the rules call for.
+        expected: usize,
+        /// How many the line lists.
+        found: usize,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            ParseError::MalformedLine{line} =>
+                write!(formatter, "line {}: expected `label: cards`", line),
+            ParseError::UnknownLabel{line, label} =>
+                write!(formatter, "line {}: unknown label '{}'", line, label),
+            ParseError::DuplicateLabel{line, label} =>
+                write!(formatter, "line {}: '{}' was already given", line, label),
+            ParseError::MissingLabel{label} =>
+                write!(formatter, "missing a line for '{}'", label),
+            ParseError::InvalidCard{line, token} =>
+                write!(formatter, "line {}: '{}' is not a card", line, token),
+            ParseError::MisplacedCard{line, token} =>
+                write!(formatter, "line {}: '{}' can't go in that cell", line, token),
+            ParseError::WrongCellCount{line, expected, found} =>
+                write!(formatter, "line {}: expected {} cells, found {}", line, expected, found),
+        }
+    }
+}
+
+fn parse_suit(chr: char) -> Option<Suit> {
+    match chr {
+        'B' => Some(Suit::Black),
+        'G' => Some(Suit::Green),
+        'R' => Some(Suit::Red),
+        'Y' => Some(Suit::Yellow),
+        _ => None,
+    }
+}
+
+fn suit_char(suit: Suit) -> char {
+    match suit {
+        Suit::Black => 'B',
+        Suit::Green => 'G',
+        Suit::Red => 'R',
+        Suit::Yellow => 'Y',
+    }
+}
+
+/// Parse a single cell token. `-` parses to None.
+pub fn parse_card(token: &str) -> Option<Option<Card>> {
+    let upper = token.to_uppercase();
+    let mut chars = upper.chars();
+    let card = match (chars.next(), chars.next(), chars.next()) {
+        (Some('-'), None, None) => return Some(None),
+        (Some('J'), None, None) => Card::JokerCard,
+        (Some('X'), None, None) => Card::DragonStack,
+        (Some(suit), Some('D'), None) => Card::DragonCard{suit: parse_suit(suit)?},
+        (Some(suit), Some(rank @ '1'..='9'), None) =>
+            Card::NumberCard{suit: parse_suit(suit)?, rank: rank as u8 - b'0'},
+        _ => return None,
+    };
+    Some(Some(card))
+}
+
+/// Format a single card as a notation token.
+pub fn format_card(card: &Card) -> String {
+    match card {
+        Card::JokerCard => String::from("J"),
+        Card::DragonCard{suit} => format!("{}D", suit_char(*suit)),
+        Card::NumberCard{suit, rank} => format!("{}{}", suit_char(*suit), rank),
+        Card::DragonStack => String::from("X"),
+    }
+}
+
+fn parse_cell_index(token: &str) -> Option<CardCellIndex> {
+    let lower = token.to_lowercase();
+    let mut chars = lower.chars();
+    let kind = chars.next()?;
+    let n = chars.as_str().parse::<usize>().ok()?.checked_sub(1)?;
+    if n >= MAX_CELLS {
+        return None;
+    }
+    match kind {
+        'f' => Some(CardCellIndex::FreeCellIndex(n)),
+        'g' => Some(CardCellIndex::GoalCellIndex(n)),
+        'c' => Some(CardCellIndex::GameCellIndex(n)),
+        _ => None,
+    }
+}
+
+fn format_cell_index(index: &CardCellIndex) -> String {
+    match *index {
+        CardCellIndex::FreeCellIndex(n) => format!("f{}", n + 1),
+        CardCellIndex::GoalCellIndex(n) => format!("g{}", n + 1),
+        CardCellIndex::GameCellIndex(n) => format!("c{}", n + 1),
+    }
+}
+
+/// Parse a move, eg `c3 f1 1` or `group G`. Whether the move can be made is up to the board.
+pub fn parse_move(text: &str) -> Option<Move> {
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    match tokens.as_slice() {
+        [group, suit] if group.to_lowercase() == "group" => {
+            let upper = suit.to_uppercase();
+            let mut chars = upper.chars();
+            match (chars.next(), chars.next()) {
+                (Some(suit), None) => Some(Move::GroupDragons{suit: parse_suit(suit)?}),
+                _ => None,
+            }
+        },
+        [source, dest, height] => Some(Move::MoveStack{
+            source: parse_cell_index(source)?,
+            dest: parse_cell_index(dest)?,
+            height: height.parse().ok()?,
+        }),
+        _ => None,
+    }
+}
+
+/// Format a move, such that `parse_move` gives it back.
+pub fn format_move(mv: &Move) -> String {
+    match mv {
+        Move::GroupDragons{suit} => format!("group {}", suit_char(*suit)),
+        Move::MoveStack{source, dest, height} =>
+            format!("{} {} {}", format_cell_index(source), format_cell_index(dest), height),
+    }
+}
+
+fn format_cell(card: Option<&Card>) -> String {
+    card.map_or(String::from("-"), format_card)
+}
+
+/// Parse every token on a line, checking each against `allowed`.
+fn parse_cells<F>(line: usize, tokens: &[&str], allowed: F) -> Result<Vec<Option<Card>>, ParseError> where
+    F: Fn(&Card) -> bool,
+{
+    tokens.iter().map(|token| match parse_card(token) {
+        Some(Some(ref card)) if !allowed(card) =>
+            Err(ParseError::MisplacedCard{line, token: token.to_string()}),
+        Some(cell) => Ok(cell),
+        None => Err(ParseError::InvalidCard{line, token: token.to_string()}),
+    }).collect()
+}
+
+fn check_count<T>(line: usize, cells: &[T], expected: usize) -> Result<(), ParseError> {
+    if cells.len() == expected {Ok(())}
+    else {Err(ParseError::WrongCellCount{line, expected, found: cells.len()})}
+}
+
+/// Parse a board written in the notation described in this module's documentation.
+pub fn parse_board(text: &str) -> Result<Board, ParseError> {
+    parse_board_with_rules(text, &RuleSet::default())
+}
+
+/// Parse a board played by `rules`, written in the notation described in this module's
+/// documentation.
+pub fn parse_board_with_rules(text: &str, rules: &RuleSet) -> Result<Board, ParseError> {
+    let mut free_cells = None;
+    let mut joker_cell = None;
+    let mut goal_cells = None;
+    let mut game_cells: Vec<Option<Vec<Card>>> = (0..rules.columns).map(|_| None).collect();
+
+    for (i, raw_line) in text.lines().enumerate() {
+        let line = i + 1;
+        let trimmed = raw_line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let colon = trimmed.find(':').ok_or(ParseError::MalformedLine{line})?;
+        let label = trimmed[..colon].trim().to_lowercase();
+        let tokens: Vec<&str> = trimmed[colon + 1..].split_whitespace().collect();
+        let duplicate = ParseError::DuplicateLabel{line, label: label.clone()};
+
+        match label.as_str() {
+            "free" => {
+                if free_cells.is_some() {return Err(duplicate);}
+                let cells = parse_cells(line, &tokens, |card| *card != Card::JokerCard)?;
+                check_count(line, &cells, rules.free_cells)?;
+                free_cells = Some(cells);
+            },
+            "joker" => {
+                if joker_cell.is_some() {return Err(duplicate);}
+                let cells = parse_cells(line, &tokens, |card| *card == Card::JokerCard)?;
+                check_count(line, &cells, 1)?;
+                joker_cell = Some(cells[0].is_some());
+            },
+            "goal" => {
+                if goal_cells.is_some() {return Err(duplicate);}
+                let cells = parse_cells(line, &tokens, |card| matches!(card, Card::NumberCard{..}))?;
+                check_count(line, &cells, rules.suits)?;
+                goal_cells = Some(cells);
+            },
+            _ => {
+                let column = match label.parse::<usize>() {
+                    Ok(n) if 1 <= n && n <= rules.columns => n - 1,
+                    _ => return Err(ParseError::UnknownLabel{line, label}),
+                };
+                if game_cells[column].is_some() {return Err(duplicate);}
+                let cells = if tokens == ["-"] {Vec::new()} else {
+                    let cells = parse_cells(line, &tokens, |card| *card != Card::DragonStack)?;
+                    if cells.iter().any(|cell| cell.is_none()) {
+                        return Err(ParseError::MisplacedCard{line, token: String::from("-")});
+                    }
+                    cells.into_iter().map(|cell| cell.expect("checked above")).collect()
+                };
+                game_cells[column] = Some(cells);
+            },
+        }
+    }
+
+    let missing = |label: &str| ParseError::MissingLabel{label: label.to_string()};
+    let free_cells = free_cells.ok_or_else(|| missing("free"))?;
+    let joker_cell = match joker_cell {
+        Some(joker_cell) => joker_cell,
+        None if rules.joker => return Err(missing("joker")),
+        None => false,
+    };
+    let goal_cells = goal_cells.ok_or_else(|| missing("goal"))?;
+    let mut columns = Vec::with_capacity(rules.columns);
+    for (i, column) in game_cells.into_iter().enumerate() {
+        columns.push(column.ok_or_else(|| missing(&(i + 1).to_string()))?);
+    }
+
+    Ok(Board::with_rules(rules, free_cells, joker_cell, goal_cells, columns)
+        .expect("cell counts were checked above"))
+}
+
+/// Write a board in the notation described in this module's documentation.
+///
+/// The result parses back into an identical board with `parse_board_with_rules`, given the
+/// board's rules.
+pub fn format_board(board: &Board) -> String {
+    let mut s = String::new();
+
+    let free: Vec<_> = board.free_cells().iter().map(|cell| format_cell(cell.top().as_deref())).collect();
+    s.push_str(&format!("free: {}\n", free.join(" ")));
+
+    let has_joker = match **board.joker_cell() {
+        CardCell::JokerCell{has_joker} => has_joker,
+        _ => unreachable!(),  // should only be a jokercell
+    };
+    if board.rules().joker {
+        s.push_str(&format!("joker: {}\n", if has_joker {"J"} else {"-"}));
+    }
+
+    let goal: Vec<_> = board.goal_cells().iter().map(|cell| format_cell(cell.top().as_deref())).collect();
+    s.push_str(&format!("goal: {}\n", goal.join(" ")));
+
+    for (i, cell) in board.game_cells().iter().enumerate() {
+        let cards: Vec<_> = match **cell {
+            CardCell::GameCell{ref card_stack} => card_stack.iter().map(|card| format_card(card)).collect(),
+            _ => unreachable!(),  // should only be gamecells
+        };
+        if cards.is_empty() {
+            s.push_str(&format!("{}: -\n", i + 1));
+        }
+        else {
+            s.push_str(&format!("{}: {}\n", i + 1, cards.join(" ")));
+        }
+    }
+    s
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::board::Seed;
+
+    const NEARLY_SOLVED: &str = "
+        # A nearly-finished game.
+        free: X x GD
+        joker: J
+        goal: r9 B9 G9
+
+        1:
+        2: -
+        3: GD GD
+        4:
+        5:
+        6: gd
+        7:
+        8:
+    ";
+
+    #[test]
+    /// Ensure a hand-written board parses into the board we expect.
+    fn parse_nearly_solved() {
+        let board = parse_board(NEARLY_SOLVED).expect("should parse");
+        let expected = Board::new(
+            vec![
+                Some(Card::DragonStack),
+                Some(Card::DragonStack),
+                Some(Card::DragonCard{suit: Suit::Green}),
+            ],
+            true,
+            vec![
+                Some(Card::NumberCard{suit: Suit::Red, rank: 9}),
+                Some(Card::NumberCard{suit: Suit::Black, rank: 9}),
+                Some(Card::NumberCard{suit: Suit::Green, rank: 9}),
+            ],
+            vec![
+                Vec::new(),
+                Vec::new(),
+                vec![
+                    Card::DragonCard{suit: Suit::Green},
+                    Card::DragonCard{suit: Suit::Green},
+                ],
+                Vec::new(),
+                Vec::new(),
+                vec![Card::DragonCard{suit: Suit::Green}],
+                Vec::new(),
+                Vec::new(),
+            ],
+        ).unwrap();
+        assert!(board == expected);
+    }
+
+    #[test]
+    /// Ensure formatting then parsing a dealt board gives back the same board, cell for cell.
+    fn round_trip() {
+        let board = Board::deal_seeded(&Seed::from_string("8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&").unwrap());
+        let text = format_board(&board);
+        let parsed = parse_board(&text).expect("should parse");
+        assert_eq!(format_board(&parsed), text);
+        assert!(parsed == board);
+    }
+
+    #[test]
+    /// Ensure boards of other rule sets round-trip too, with as many cells as their rules say.
+    fn round_trip_with_rules() {
+        let rules = RuleSet::from_string("free=4,columns=9,suits=4,joker=no").unwrap();
+        let board = Board::deal_with_rules(&Seed::from_string("8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&").unwrap(), &rules);
+        let text = format_board(&board);
+        assert!(!text.contains("joker:"));
+        assert!(text.contains("9: "));
+        let parsed = parse_board_with_rules(&text, &rules).expect("should parse");
+        assert_eq!(format_board(&parsed), text);
+        assert!(parsed == board);
+
+        // The real game's rules have fewer cells.
+        assert!(parse_board(&text).is_err());
+    }
+
+    #[test]
+    fn moves() {
+        let mv = Move::MoveStack{
+            source: CardCellIndex::GameCellIndex(2),
+            dest: CardCellIndex::FreeCellIndex(0),
+            height: 1,
+        };
+        assert_eq!(format_move(&mv), "c3 f1 1");
+        assert_eq!(parse_move("C3  f1 1"), Some(mv));
+        let mv = Move::GroupDragons{suit: Suit::Green};
+        assert_eq!(format_move(&mv), "group G");
+        assert_eq!(parse_move("GROUP g"), Some(mv));
+        assert!(parse_move("g1 f1 1").is_some());
+        assert!(parse_move("c3 g1 1").is_some());
+        for invalid in &["", "c3 f1", "c10 f1 1", "c0 f1 1", "f1 c3 x", "group", "group D", "c3 f1 1 1"] {
+            assert_eq!(parse_move(invalid), None);
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            parse_board("free - - -").err(),
+            Some(ParseError::MalformedLine{line: 1}),
+        );
+        assert_eq!(
+            parse_board("free: - -").err(),
+            Some(ParseError::WrongCellCount{line: 1, expected: 3, found: 2}),
+        );
+        assert_eq!(
+            parse_board("\ngoal: GD - -").err(),
+            Some(ParseError::MisplacedCard{line: 2, token: String::from("GD")}),
+        );
+        assert_eq!(
+            parse_board("1: G0").err(),
+            Some(ParseError::InvalidCard{line: 1, token: String::from("G0")}),
+        );
+        assert_eq!(
+            parse_board("9: G1").err(),
+            Some(ParseError::UnknownLabel{line: 1, label: String::from("9")}),
+        );
+        assert_eq!(
+            parse_board("1: G1\n1: G2").err(),
+            Some(ParseError::DuplicateLabel{line: 2, label: String::from("1")}),
+        );
+        assert_eq!(
+            parse_board("free: - - -\njoker: -\n1: G1").err(),
+            Some(ParseError::MissingLabel{label: String::from("goal")}),
+        );
+    }
+}
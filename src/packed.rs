@@ -0,0 +1,244 @@
+//! A compact form of `Board` for the solver to hold on to while it searches.
+//!
+//! A `Board` is a handful of vectors of reference-counted cells, each holding reference-counted
+//! cards, and comparing two of them sorts their cells first. A `PackedBoard` is a single string of
+//! bytes, one per card, with its cells already in a canonical order: boards which are equal as
+//! `Board`s pack to the very same bytes, so comparing them is just comparing bytes. Hashing them
+//! is cheaper still: they keep the hash of the board they were packed from.
+//!
+//! The bytes are the joker cell, then the goal cells, then the free cells, then each game cell
+//! from the bottom card up followed by `EMPTY`. The goal, free and game cells are each sorted.
+//! Packed boards don't know what rules they were played by, so unpacking one needs the rules
+//! again.
+
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use ::board::{Board, Card, CardCell, RuleSet, Suit};
+
+/// An empty free or goal cell, and the end of a game cell.
+const EMPTY: u8 = 0;
+const JOKER: u8 = 0x0c;
+const DRAGON_STACK: u8 = 0x0d;
+/// Added to a suit's number cards' ranks, and its dragon's code.
+const DRAGON: u8 = 0x0a;
+
+fn suit_code(suit: Suit) -> u8 {
+    let index = match suit {
+        Suit::Black => 1,
+        Suit::Green => 2,
+        Suit::Red => 3,
+        Suit::Yellow => 4,
+    };
+    index << 4
+}
+
+fn code_suit(code: u8) -> Suit {
+    match code >> 4 {
+        1 => Suit::Black,
+        2 => Suit::Green,
+        3 => Suit::Red,
+        4 => Suit::Yellow,
+        _ => panic!("invalid packed card {:#x}", code),
+    }
+}
+
+/// A nonzero byte standing for `card`.
+fn pack_card(card: &Card) -> u8 {
+    match *card {
+        Card::JokerCard => JOKER,
+        Card::DragonStack => DRAGON_STACK,
+        Card::DragonCard{suit} => suit_code(suit) | DRAGON,
+        Card::NumberCard{suit, rank} => suit_code(suit) | rank,
+    }
+}
+
+fn unpack_card(code: u8) -> Card {
+    match code {
+        JOKER => Card::JokerCard,
+        DRAGON_STACK => Card::DragonStack,
+        _ if code & 0x0f == DRAGON => Card::DragonCard{suit: code_suit(code)},
+        _ => Card::NumberCard{suit: code_suit(code), rank: code & 0x0f},
+    }
+}
+
+fn pack_top(cell: &CardCell) -> u8 {
+    cell.top().map_or(EMPTY, |card| pack_card(&card))
+}
+
+fn unpack_top(code: u8) -> Option<Card> {
+    if code == EMPTY {None} else {Some(unpack_card(code))}
+}
+
+/// A board packed into a canonical string of bytes. Clones share the bytes.
+#[derive(Clone, Debug)]
+pub struct PackedBoard {
+    key: Arc<[u8]>,
+    /// The packed board's `zobrist_hash`.
+    hash: u64,
+}
+
+impl PackedBoard {
+    pub fn pack(board: &Board) -> PackedBoard {
+        let mut goals: Vec<u8> = board.goal_cells().iter().map(|cell| pack_top(cell)).collect();
+        goals.sort();
+        let mut frees: Vec<u8> = board.free_cells().iter().map(|cell| pack_top(cell)).collect();
+        frees.sort();
+        let mut columns: Vec<Vec<u8>> = board.game_cells().iter().map(|cell| match **cell {
+            CardCell::GameCell{ref card_stack} => card_stack.iter().map(|card| pack_card(card)).collect(),
+            _ => unreachable!(),  // should only be gamecells
+        }).collect();
+        columns.sort();
+
+        let mut key = Vec::with_capacity(
+            1 + goals.len() + frees.len() + columns.iter().map(|column| column.len() + 1).sum::<usize>()
+        );
+        key.push(board.joker_cell().len() as u8);
+        key.extend(goals);
+        key.extend(frees);
+        for column in columns {
+            key.extend(column);
+            key.push(EMPTY);
+        }
+        PackedBoard{key: key.into(), hash: board.zobrist_hash()}
+    }
+
+    /// The board this was packed from, or one equal to it, given the rules it was played by.
+    pub fn unpack(&self, rules: &RuleSet) -> Board {
+        let key = &self.key;
+        let goals = 1;
+        let frees = goals + rules.suits;
+        let mut game_cells = Vec::with_capacity(rules.columns);
+        let mut column = Vec::new();
+        for &code in key[frees + rules.free_cells..].iter() {
+            if code == EMPTY {
+                game_cells.push(column);
+                column = Vec::new();
+            }
+            else {
+                column.push(unpack_card(code));
+            }
+        }
+        Board::with_rules(
+            rules,
+            key[frees..frees + rules.free_cells].iter().map(|&code| unpack_top(code)).collect(),
+            key[0] == 1,
+            key[goals..frees].iter().map(|&code| unpack_top(code)).collect(),
+            game_cells,
+        ).expect("packed by the same rules")
+    }
+
+    /// The canonical bytes themselves. Boards are equal exactly when these are.
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+}
+
+impl PartialEq for PackedBoard {
+    fn eq(&self, rhs: &PackedBoard) -> bool {
+        self.hash == rhs.hash && self.key == rhs.key
+    }
+}
+
+impl Eq for PackedBoard {}
+
+impl Hash for PackedBoard {
+    fn hash<H>(&self, hasher: &mut H) where
+        H: Hasher,
+    {
+        hasher.write_u64(self.hash);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+    use ::board::Seed;
+    use ::solver::next_states;
+
+    /// Counts the bytes each thread has allocated and not yet freed, so a test can see how much
+    /// what it holds on to takes.
+    struct CountingAllocator;
+
+    thread_local! {
+        static LIVE_BYTES: Cell<isize> = const {Cell::new(0)};
+    }
+
+    fn count(bytes: isize) {
+        // Fails only while the thread is being torn down, when nothing is measuring it.
+        let _ = LIVE_BYTES.try_with(|live| live.set(live.get() + bytes));
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            count(layout.size() as isize);
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            count(-(layout.size() as isize));
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn live_bytes() -> isize {
+        LIVE_BYTES.with(Cell::get)
+    }
+
+    #[test]
+    /// Ensure packing keeps every card, and that boards pack to the same bytes exactly when they
+    /// are equal.
+    fn round_trip() {
+        let board = Board::deal_seeded(&Seed::from_string("8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&").unwrap())
+            .do_automoves();
+        let packed = PackedBoard::pack(&board);
+        assert!(packed.unpack(board.rules()) == board);
+
+        let next: Vec<Board> = next_states(&board, Default::default()).into_iter()
+            .map(|(_, next)| next).collect();
+        for left in next.iter() {
+            let packed_left = PackedBoard::pack(left);
+            assert!(packed_left.unpack(left.rules()) == *left);
+            assert_eq!(PackedBoard::pack(&packed_left.unpack(left.rules())), packed_left);
+            assert_eq!(packed_left.hash, left.zobrist_hash());
+            for right in next.iter() {
+                assert_eq!(packed_left == PackedBoard::pack(right), left == right);
+            }
+        }
+
+        let rules = RuleSet::from_string("free=4,columns=9,suits=4,joker=no").unwrap();
+        let board = Board::deal_with_rules(&Seed::random(), &rules);
+        assert!(PackedBoard::pack(&board).unpack(&rules) == board);
+    }
+
+    #[test]
+    /// Ensure packed boards take a fraction of the memory of the boards they were packed from,
+    /// measured over boards reached from a deal the way the search reaches them: each one shares
+    /// the cells the move to it left alone with the board it was reached from.
+    fn memory() {
+        let board = Board::deal_seeded(&Seed::from_string("8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&").unwrap())
+            .do_automoves();
+        let before = live_bytes();
+        let mut boards = vec![board];
+        let mut i = 0;
+        while boards.len() < 1000 {
+            let next = next_states(&boards[i], Default::default());
+            boards.extend(next.into_iter().map(|(_, next)| next));
+            i += 1;
+        }
+        boards.shrink_to_fit();
+        let unpacked = (live_bytes() - before) as usize / boards.len();
+
+        let before = live_bytes();
+        let packed: Vec<PackedBoard> = boards.iter().map(PackedBoard::pack).collect();
+        let packed = (live_bytes() - before) as usize / packed.len();
+        // About 330 and 95 bytes, when measured.
+        assert!(packed * 3 < unpacked, "{} bytes packed, {} unpacked", packed, unpacked);
+    }
+}
@@ -0,0 +1,194 @@
+//! Rating how hard deals are, from how much work the solver has to do to win them.
+
+use std::fmt;
+
+use ::board::{Board, CardCellIndex, Move};
+use ::solver::{self, SolveError, SolveOutcome, SolverConfig};
+
+/// How many nodes `difficulty` may expand before calling a deal expert, since a deal the solver
+/// can't win quickly is no easier for a person.
+pub const DEFAULT_MAX_NODES: u64 = 200_000;
+
+/// Lowest score of each tier above easy. These are the quartiles of the scores of the 183 deals the
+/// solver won, out of 200 random deals rated with `DEFAULT_MAX_NODES` (`rate` with no options, 200
+/// times): 45 of them came out easy, 42 medium, 50 hard and 46 expert, and the 17 it gave up on
+/// are expert as well.
+const MEDIUM_SCORE: u32 = 78;
+const HARD_SCORE: u32 = 86;
+const EXPERT_SCORE: u32 = 96;
+
+/// How hard a deal is, in a word. Each tier is a range of scores, set so that about a quarter of
+/// the deals the solver wins land in each; see `Difficulty::score`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Tier {
+    /// The lowest scores.
+    Easy,
+    /// Scores a little below the median.
+    Medium,
+    /// Scores a little above the median.
+    Hard,
+    /// The highest scores, and deals the solver gave up on.
+    Expert,
+}
+
+impl Tier {
+    fn from_score(score: u32) -> Tier {
+        if score >= EXPERT_SCORE {Tier::Expert}
+        else if score >= HARD_SCORE {Tier::Hard}
+        else if score >= MEDIUM_SCORE {Tier::Medium}
+        else {Tier::Easy}
+    }
+}
+
+impl fmt::Display for Tier {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        formatter.pad(match self {
+            Tier::Easy => "easy",
+            Tier::Medium => "medium",
+            Tier::Hard => "hard",
+            Tier::Expert => "expert",
+        })
+    }
+}
+
+/// How hard a board is, and what went into deciding that.
+#[derive(Clone, Debug)]
+pub struct Difficulty {
+    /// Higher is harder.
+    pub score: u32,
+    /// None if there's no way to win at all.
+    pub tier: Option<Tier>,
+    /// Length of the solution the solver found, if it found one. This isn't necessarily the
+    /// shortest solution.
+    pub moves: Option<usize>,
+    /// How many boards the solver expanded, whether or not it won.
+    pub nodes_expanded: u64,
+    /// How many of the moves in the solution the solver found put a card in a free cell. Like
+    /// `moves`, this depends on which solution it found: another might need more or fewer.
+    pub solution_free_cell_moves: usize,
+    /// How many dragons start trapped under dragons of the same suit.
+    pub trapped_dragons: u32,
+    /// The effective branching factor of the search: how many boards it expanded per move of the
+    /// solution, compounded. 1.0 means it went straight to the solution.
+    pub branching_factor: f64,
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.tier {
+            Some(tier) => writeln!(f, "difficulty:      {} ({})", tier, self.score)?,
+            None => writeln!(f, "difficulty:      unwinnable")?,
+        }
+        match self.moves {
+            Some(moves) => writeln!(f, "moves:           {}", moves)?,
+            None => writeln!(f, "moves:           -")?,
+        }
+        writeln!(f, "nodes expanded:  {}", self.nodes_expanded)?;
+        writeln!(f, "free cell moves: {}", self.solution_free_cell_moves)?;
+        writeln!(f, "trapped dragons: {}", self.trapped_dragons)?;
+        write!(f, "branching:       {:.3}", self.branching_factor)
+    }
+}
+
+/// Rate how hard it is to win from `board`, within the solver's default budget for rating.
+pub fn difficulty(board: &Board) -> Result<Difficulty, SolveError> {
+    let config = SolverConfig{max_nodes: Some(DEFAULT_MAX_NODES), ..SolverConfig::default()};
+    difficulty_with(board, &config)
+}
+
+/// Rate how hard it is to win from `board`, solving it within the limits of `config`. A board the
+/// solver gives up on is rated expert.
+pub fn difficulty_with(board: &Board, config: &SolverConfig) -> Result<Difficulty, SolveError> {
+    let report = solver::solve(board, config)?;
+    let nodes_expanded = report.stats.nodes_expanded;
+    let trapped_dragons = solver::trapped_dragons(board);
+    let (moves, solution_free_cell_moves) = match report.outcome {
+        SolveOutcome::Solved(ref solution) => {
+            let free_cell_moves = solution.moves.iter().filter(|mv| match mv {
+                Move::MoveStack{dest, ..} => matches!(dest, CardCellIndex::FreeCellIndex(_)),
+                Move::GroupDragons{..} => false,
+            }).count();
+            (Some(solution.moves.len()), free_cell_moves)
+        },
+        SolveOutcome::ProvenUnsolvable | SolveOutcome::BudgetExhausted{..} => (None, 0),
+    };
+    let branching_factor = match moves {
+        Some(moves) if moves > 0 => (nodes_expanded.max(1) as f64).powf(1.0 / moves as f64),
+        _ => 1.0,
+    };
+
+    let mut score = moves.unwrap_or(0) as u32
+        + 2 * solution_free_cell_moves as u32
+        + 3 * trapped_dragons
+        + (10.0 * (nodes_expanded.max(1) as f64).log10()) as u32
+        + (20.0 * (branching_factor - 1.0)) as u32;
+    let tier = match report.outcome {
+        SolveOutcome::Solved(_) => Some(Tier::from_score(score)),
+        SolveOutcome::BudgetExhausted{..} => {
+            // Without a solution, the score is missing most of what would make it count as hard.
+            score = score.max(EXPERT_SCORE);
+            Some(Tier::Expert)
+        },
+        SolveOutcome::ProvenUnsolvable => None,
+    };
+    Ok(Difficulty{
+        score,
+        tier,
+        moves,
+        nodes_expanded,
+        solution_free_cell_moves,
+        trapped_dragons,
+        branching_factor,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::board::{Card, RuleSet, Suit};
+    use ::notation;
+
+    #[test]
+    fn tiers() {
+        assert_eq!(Tier::from_score(0), Tier::Easy);
+        assert_eq!(Tier::from_score(MEDIUM_SCORE), Tier::Medium);
+        assert_eq!(Tier::from_score(HARD_SCORE - 1), Tier::Medium);
+        assert_eq!(Tier::from_score(HARD_SCORE), Tier::Hard);
+        assert_eq!(Tier::from_score(EXPERT_SCORE + 100), Tier::Expert);
+    }
+
+    #[test]
+    /// Ensure a board a couple of moves from solved is easy, and a hopeless one is unwinnable.
+    fn rate() {
+        let board = notation::parse_board(indoc!("
+            free: GD X X
+            joker: J
+            goal: R9 B4 G1
+            1:
+            2: G4
+            3: B9 G8 B7 G6
+            4: B5 G3
+            5: GD G2 GD
+            6: GD
+            7:
+            8: G9 B8 G7 B6 G5
+        ")).unwrap();
+        let difficulty = difficulty(&board).expect("valid board");
+        assert_eq!(difficulty.tier, Some(Tier::Easy));
+        assert_eq!(difficulty.moves, Some(2));
+        assert_eq!(difficulty.trapped_dragons, 1);
+        assert_eq!(difficulty.solution_free_cell_moves, 0);
+
+        // The 2 is stuck on the 1, with nowhere else to go.
+        let rules = RuleSet::from_string("free=0,columns=1,suits=1,ranks=2,dragons=0,joker=no")
+            .unwrap();
+        let board = Board::with_rules(&rules, vec![], false, vec![None], vec![vec![
+            Card::NumberCard{suit: Suit::Black, rank: 1},
+            Card::NumberCard{suit: Suit::Black, rank: 2},
+        ]]).unwrap();
+        let difficulty = difficulty_with(&board, &SolverConfig::default()).expect("valid board");
+        assert_eq!(difficulty.tier, None);
+        assert_eq!(difficulty.moves, None);
+    }
+}
@@ -0,0 +1,320 @@
+//! Saving games in progress, so they can be picked up again later.
+//!
+//! A saved game is the board, in the notation described in `notation`, along with a few more
+//! labelled lines saying how the game got there:
+//!
+//! ```text
+//! seed: 8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&
+//! rules: free=3,columns=8,suits=3,ranks=9,dragons=4,joker=yes
+//! elapsed: 83.250
+//! automoves: game
+//! cursor: 11
+//! move: c8 f1 1
+//! move: group G
+//! undone: c2 c1 1
+//! free: RD X -
+//! ...
+//! ```
+//!
+//! - `seed` is the seed the game was dealt from.
+//! - `rules` is the rule set the game is played by, as `RuleSet::from_string` takes it. It may be
+//!   left out, for the real game's.
+//! - `elapsed` is how many seconds the game has been played for.
+//! - `automoves` is which cards were moved to the goal automatically: `game`, `conservative` or
+//!   `none`. It may be left out, for `game`.
+//! - `cursor` is where the cursor was.
+//! - `move` lines are the moves made since the deal, in order.
+//! - `undone` lines are moves which were undone and may be redone, next to be redone first.
+use std::fmt;
+use std::time::Duration;
+
+use ::board::{AutoMovePolicy, Board, Move, RuleSet, RuleSetError, Seed, SeedError};
+use ::notation::{self, ParseError};
+use ::util::as_seconds;
+
+/// Everything needed to pick a game up where it was left.
+pub struct SavedGame {
+    pub seed: Seed,
+    pub rules: RuleSet,
+    pub board: Board,
+    pub moves: Vec<Move>,
+    pub undone: Vec<Move>,
+    pub elapsed: Duration,
+    pub automoves: AutoMovePolicy,
+    pub cursor: u8,
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    /// The board, or a line the board notation would accept, is wrong.
+    Board(ParseError),
+    InvalidSeed{line: usize, err: SeedError},
+    InvalidRules{line: usize, err: RuleSetError},
+    /// A value that doesn't parse, eg a cursor that isn't a number.
+    InvalidValue{line: usize, label: &'static str},
+    /// The same label appears on more than one line, for a label that may only appear once.
+    DuplicateLabel{line: usize, label: &'static str},
+    /// A required label appears on no line.
+    MissingLabel{label: &'static str},
+    /// A move that can't be made on the board it's made on.
+    IllegalMove{line: usize},
+    /// Making the moves doesn't lead to the board that was saved.
+    BoardMismatch,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            LoadError::Board(err) => write!(formatter, "{}", err),
+            LoadError::InvalidSeed{line, err} => write!(formatter, "line {}: {}", line, err),
+            LoadError::InvalidRules{line, err} => write!(formatter, "line {}: {}", line, err),
+            LoadError::InvalidValue{line, label} =>
+                write!(formatter, "line {}: not a valid {}", line, label),
+            LoadError::DuplicateLabel{line, label} =>
+                write!(formatter, "line {}: '{}' was already given", line, label),
+            LoadError::MissingLabel{label} => write!(formatter, "missing a line for '{}'", label),
+            LoadError::IllegalMove{line} =>
+                write!(formatter, "line {}: that move can't be made from there", line),
+            LoadError::BoardMismatch =>
+                write!(formatter, "the moves don't lead to the board that was saved"),
+        }
+    }
+}
+
+/// Make each of `moves` and the automoves following it, starting from `board`. Returns every
+/// board along the way, starting with `board`, or the index of the first move that can't be made.
+pub fn replay(board: Board, moves: &[Move], automoves: AutoMovePolicy) -> Result<Vec<Board>, usize> {
+    let mut boards = vec![board];
+    for (i, mv) in moves.iter().enumerate() {
+        let next = boards.last().expect("never empty").apply_move(mv).ok_or(i)?
+            .do_automoves_with(automoves);
+        boards.push(next);
+    }
+    Ok(boards)
+}
+
+/// The board a game dealt from `seed` by `rules` starts on, once the first automoves are made.
+pub fn starting_board(seed: &Seed, rules: &RuleSet, automoves: AutoMovePolicy) -> Board {
+    Board::deal_with_rules(seed, rules).do_automoves_with(automoves)
+}
+
+pub fn format_saved_game(game: &SavedGame) -> String {
+    let mut s = String::new();
+    s.push_str(&format!("seed: {}\n", game.seed));
+    s.push_str(&format!("rules: {}\n", game.rules));
+    s.push_str(&format!("elapsed: {:.3}\n", as_seconds(game.elapsed)));
+    s.push_str(&format!("automoves: {}\n", game.automoves));
+    s.push_str(&format!("cursor: {}\n", game.cursor));
+    for mv in game.moves.iter() {
+        s.push_str(&format!("move: {}\n", notation::format_move(mv)));
+    }
+    for mv in game.undone.iter() {
+        s.push_str(&format!("undone: {}\n", notation::format_move(mv)));
+    }
+    s.push_str(&notation::format_board(&game.board));
+    s
+}
+
+/// Parse a saved game, and check its moves really do lead from the deal to its board.
+pub fn parse_saved_game(text: &str) -> Result<SavedGame, LoadError> {
+    let mut seed = None;
+    let mut rules = None;
+    let mut elapsed = None;
+    let mut automoves = None;
+    let mut cursor = None;
+    let mut moves = Vec::new();
+    let mut undone = Vec::new();
+    // Lines for the board notation, with the ones it wouldn't understand blanked out so any
+    // errors it finds are reported on the right line.
+    let mut board_lines = Vec::new();
+
+    for (i, raw_line) in text.lines().enumerate() {
+        let line = i + 1;
+        let (label, value) = match raw_line.find(':') {
+            Some(colon) =>
+                (raw_line[..colon].trim().to_lowercase(), raw_line[colon + 1..].trim()),
+            None => {
+                board_lines.push(raw_line);
+                continue;
+            },
+        };
+        match label.as_str() {
+            "seed" => {
+                if seed.is_some() {return Err(LoadError::DuplicateLabel{line, label: "seed"});}
+                seed = Some(Seed::from_string(value).map_err(|err| LoadError::InvalidSeed{line, err})?);
+            },
+            "rules" => {
+                if rules.is_some() {return Err(LoadError::DuplicateLabel{line, label: "rules"});}
+                rules = Some(RuleSet::from_string(value).map_err(|err| LoadError::InvalidRules{line, err})?);
+            },
+            "elapsed" => {
+                if elapsed.is_some() {return Err(LoadError::DuplicateLabel{line, label: "elapsed"});}
+                let seconds: f64 = value.parse().ok().filter(|seconds| *seconds >= 0.0)
+                    .ok_or(LoadError::InvalidValue{line, label: "elapsed"})?;
+                elapsed = Some(Duration::from_millis((seconds * 1000.0) as u64));
+            },
+            "automoves" => {
+                if automoves.is_some() {return Err(LoadError::DuplicateLabel{line, label: "automoves"});}
+                automoves = Some(AutoMovePolicy::from_name(value)
+                    .ok_or(LoadError::InvalidValue{line, label: "automoves"})?);
+            },
+            "cursor" => {
+                if cursor.is_some() {return Err(LoadError::DuplicateLabel{line, label: "cursor"});}
+                // Whether it's in range depends on the rules, which may not have been read yet.
+                cursor = Some((line, value.parse::<u8>().ok()
+                    .ok_or(LoadError::InvalidValue{line, label: "cursor"})?));
+            },
+            "move" => moves.push((line, notation::parse_move(value)
+                .ok_or(LoadError::InvalidValue{line, label: "move"})?)),
+            "undone" => undone.push((line, notation::parse_move(value)
+                .ok_or(LoadError::InvalidValue{line, label: "undone"})?)),
+            _ => {
+                board_lines.push(raw_line);
+                continue;
+            },
+        }
+        board_lines.push("");
+    }
+
+    let rules = rules.unwrap_or_default();
+    let board = notation::parse_board_with_rules(&board_lines.join("\n"), &rules)
+        .map_err(LoadError::Board)?;
+    let seed = seed.ok_or(LoadError::MissingLabel{label: "seed"})?;
+    let (cursor_line, cursor) = cursor.ok_or(LoadError::MissingLabel{label: "cursor"})?;
+    if cursor < 1 || cursor as usize > rules.free_cells + rules.suits + rules.columns {
+        return Err(LoadError::InvalidValue{line: cursor_line, label: "cursor"});
+    }
+    let saved = SavedGame{
+        board,
+        moves: moves.iter().map(|&(_, mv)| mv).collect(),
+        undone: undone.iter().map(|&(_, mv)| mv).collect(),
+        elapsed: elapsed.ok_or(LoadError::MissingLabel{label: "elapsed"})?,
+        automoves: automoves.unwrap_or_default(),
+        cursor,
+        seed,
+        rules,
+    };
+
+    let start = starting_board(&saved.seed, &saved.rules, saved.automoves);
+    let boards = replay(start, &saved.moves, saved.automoves)
+        .map_err(|i| LoadError::IllegalMove{line: moves[i].0})?;
+    if boards.last().expect("never empty") != &saved.board {
+        return Err(LoadError::BoardMismatch);
+    }
+    replay(saved.board.clone(), &saved.undone, saved.automoves).map_err(|i| LoadError::IllegalMove{line: undone[i].0})?;
+    Ok(saved)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::board::{CardCellIndex, Suit};
+
+    fn saved_game() -> SavedGame {
+        let seed = Seed::from_string("8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&").unwrap();
+        let moves = vec![Move::MoveStack{
+            source: CardCellIndex::GameCellIndex(7),
+            dest: CardCellIndex::FreeCellIndex(0),
+            height: 1,
+        }];
+        let automoves = AutoMovePolicy::GameAccurate;
+        let rules = RuleSet::default();
+        let board = replay(starting_board(&seed, &rules, automoves), &moves, automoves).unwrap().pop().unwrap();
+        SavedGame{
+            seed,
+            rules,
+            board,
+            moves,
+            undone: vec![Move::MoveStack{
+                source: CardCellIndex::GameCellIndex(6),
+                dest: CardCellIndex::FreeCellIndex(1),
+                height: 1,
+            }],
+            elapsed: Duration::from_millis(83250),
+            automoves,
+            cursor: 11,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = format_saved_game(&saved_game());
+        let parsed = parse_saved_game(&text).expect("should parse");
+        assert_eq!(format_saved_game(&parsed), text);
+        assert_eq!(parsed.elapsed, Duration::from_millis(83250));
+        assert_eq!(parsed.cursor, 11);
+
+        // Saves from before automoves could be changed were always of the game's automoves.
+        let parsed = parse_saved_game(&text.replace("automoves: game\n", "")).expect("should parse");
+        assert_eq!(parsed.automoves, AutoMovePolicy::GameAccurate);
+        // And the same goes for rule sets.
+        let rules = "rules: free=3,columns=8,suits=3,ranks=9,dragons=4,joker=yes\n";
+        let parsed = parse_saved_game(&text.replace(rules, "")).expect("should parse");
+        assert_eq!(parsed.rules, RuleSet::default());
+    }
+
+    #[test]
+    fn round_trip_with_rules() {
+        let seed = Seed::from_string("8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&").unwrap();
+        let rules = RuleSet::from_string("free=4,columns=9,suits=4,joker=no").unwrap();
+        let automoves = AutoMovePolicy::GameAccurate;
+        let game = SavedGame{
+            board: starting_board(&seed, &rules, automoves),
+            seed,
+            rules,
+            moves: vec![],
+            undone: vec![],
+            elapsed: Duration::from_millis(0),
+            automoves,
+            cursor: 17,
+        };
+        let text = format_saved_game(&game);
+        let parsed = parse_saved_game(&text).expect("should parse");
+        assert_eq!(parsed.rules, game.rules);
+        assert_eq!(format_saved_game(&parsed), text);
+
+        match parse_saved_game(&text.replace("cursor: 17", "cursor: 18")) {
+            Err(LoadError::InvalidValue{line: 5, label: "cursor"}) => (),
+            _ => panic!("expected an invalid cursor"),
+        }
+    }
+
+    #[test]
+    fn load_errors() {
+        let text = format_saved_game(&saved_game());
+        match parse_saved_game(&text.replace("cursor: 11", "cursor: 15")) {
+            Err(LoadError::InvalidValue{line: 5, label: "cursor"}) => (),
+            _ => panic!("expected an invalid cursor"),
+        }
+        match parse_saved_game(&text.replace("automoves: game", "automoves: some")) {
+            Err(LoadError::InvalidValue{line: 4, label: "automoves"}) => (),
+            _ => panic!("expected an invalid automove policy"),
+        }
+        match parse_saved_game(&text.replace("move: c8 f1 1", "move: c8 g1 1")) {
+            Err(LoadError::IllegalMove{line: 6}) => (),
+            _ => panic!("expected an illegal move"),
+        }
+        match parse_saved_game(&text.replace("move: c8 f1 1", "move: g1 f1 1")) {
+            Err(LoadError::IllegalMove{line: 6}) => (),
+            _ => panic!("expected a move out of a goal cell to be illegal"),
+        }
+        match parse_saved_game(&text.replace("move: c8 f1 1\n", "")) {
+            Err(LoadError::BoardMismatch) => (),
+            _ => panic!("expected the board not to match"),
+        }
+        match parse_saved_game(&text.replace("joker:", "jester:")) {
+            Err(LoadError::Board(ParseError::UnknownLabel{line: 9, ..})) => (),
+            _ => panic!("expected the board notation's error"),
+        }
+        let grouped = SavedGame{undone: vec![Move::GroupDragons{suit: Suit::Red}], ..saved_game()};
+        match parse_saved_game(&format_saved_game(&grouped)) {
+            Err(LoadError::IllegalMove{line: 7}) => (),
+            _ => panic!("expected an illegal undone move"),
+        }
+        match parse_saved_game(&text.replace("suits=3", "suits=5")) {
+            Err(LoadError::InvalidRules{line: 2, ..}) => (),
+            _ => panic!("expected invalid rules"),
+        }
+    }
+}
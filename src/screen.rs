@@ -0,0 +1,61 @@
+//! Drawing full-screen on the terminal's alternate screen, so each frame replaces the last
+//! instead of scrolling.
+
+use std::io::{self, Write};
+use std::panic::{self, PanicHookInfo};
+use std::sync::Arc;
+
+/// Switch to the alternate screen, hide the cursor, and report mouse presses in SGR mode.
+const ENTER: &str = "\x1b[?1049h\x1b[?25l\x1b[?1000h\x1b[?1006h";
+const LEAVE: &str = "\x1b[?1006l\x1b[?1000l\x1b[?25h\x1b[?1049l";
+
+type PanicHook = dyn Fn(&PanicHookInfo) + Sync + Send;
+
+/// The alternate screen, with the cursor hidden and mouse presses reported. The terminal is put
+/// back as it was when this is dropped, or if the program panics first.
+pub struct Screen {
+    /// The panic hook from before the screen was entered, to be put back once it's left.
+    previous_hook: Arc<PanicHook>,
+}
+
+impl Screen {
+    pub fn new() -> Screen {
+        let previous_hook: Arc<PanicHook> = Arc::from(panic::take_hook());
+        let hook = previous_hook.clone();
+        // Leave before the panic message is printed, or it'd be lost with the alternate screen.
+        panic::set_hook(Box::new(move |info| {
+            leave();
+            hook(info);
+        }));
+        print!("{}", ENTER);
+        let _ = io::stdout().flush();
+        Screen{previous_hook}
+    }
+
+    /// Replace whatever's on screen with `text`.
+    pub fn draw(&mut self, text: &str) {
+        let mut frame = String::from("\x1b[H");
+        for line in text.lines() {
+            // Clear whatever's left of the last frame's line.
+            frame.push_str(line);
+            frame.push_str("\x1b[K\n");
+        }
+        // And whatever's left of the last frame below this one.
+        frame.push_str("\x1b[J");
+        print!("{}", frame);
+        let _ = io::stdout().flush();
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        leave();
+        let previous_hook = self.previous_hook.clone();
+        panic::set_hook(Box::new(move |info| previous_hook(info)));
+    }
+}
+
+fn leave() {
+    print!("{}", LEAVE);
+    let _ = io::stdout().flush();
+}
@@ -1,39 +1,33 @@
+//! Searching for ways to win boards, and the limits searches are held to.
+
 use std::cmp::Ordering;
-use std::collections::{HashMap, HashSet, VecDeque, BinaryHeap};
-use std::hash::Hash;
-use std::rc::Rc;
-
-use ::board::{Board, Card, CardCellIndex, CardCell, MoveStackError, Suit};
-
-const SOURCE_SLOTS: &[CardCellIndex] = &[
-    CardCellIndex::FreeCellIndex(0),
-    CardCellIndex::FreeCellIndex(1),
-    CardCellIndex::FreeCellIndex(2),
-    CardCellIndex::GameCellIndex(0),
-    CardCellIndex::GameCellIndex(1),
-    CardCellIndex::GameCellIndex(2),
-    CardCellIndex::GameCellIndex(3),
-    CardCellIndex::GameCellIndex(4),
-    CardCellIndex::GameCellIndex(5),
-    CardCellIndex::GameCellIndex(6),
-    CardCellIndex::GameCellIndex(7),
-];
-const DEST_SLOTS: &[CardCellIndex] = &[
-    CardCellIndex::GoalCellIndex(0),
-    CardCellIndex::GoalCellIndex(1),
-    CardCellIndex::GoalCellIndex(2),
-    CardCellIndex::FreeCellIndex(0),
-    CardCellIndex::FreeCellIndex(1),
-    CardCellIndex::FreeCellIndex(2),
-    CardCellIndex::GameCellIndex(0),
-    CardCellIndex::GameCellIndex(1),
-    CardCellIndex::GameCellIndex(2),
-    CardCellIndex::GameCellIndex(3),
-    CardCellIndex::GameCellIndex(4),
-    CardCellIndex::GameCellIndex(5),
-    CardCellIndex::GameCellIndex(6),
-    CardCellIndex::GameCellIndex(7),
-];
+use std::collections::{HashMap, VecDeque, BinaryHeap};
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize};
+use std::sync::atomic::Ordering::Relaxed;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ::board::{AutoMovePolicy, Board, Card, CardCellIndex, CardCell, Move, MoveStackError, RuleSet, Seed, Violation};
+use ::packed::PackedBoard;
+
+/// Cells a move may be made from: every free cell, then every game cell.
+fn source_slots(rules: &RuleSet) -> Vec<CardCellIndex> {
+    (0..rules.free_cells).map(CardCellIndex::FreeCellIndex)
+        .chain((0..rules.columns).map(CardCellIndex::GameCellIndex))
+        .collect()
+}
+
+/// Cells a move may be made to: every goal cell, then every free cell, then every game cell.
+fn dest_slots(rules: &RuleSet) -> Vec<CardCellIndex> {
+    (0..rules.suits).map(CardCellIndex::GoalCellIndex)
+        .chain((0..rules.free_cells).map(CardCellIndex::FreeCellIndex))
+        .chain((0..rules.columns).map(CardCellIndex::GameCellIndex))
+        .collect()
+}
 
 fn counter<T, I>(iter: I) -> HashMap<T, u32> where
     T: Hash + Eq,
@@ -48,24 +42,54 @@ fn counter<T, I>(iter: I) -> HashMap<T, u32> where
 }
 
 #[derive(Eq, PartialEq)]
-struct AStarState {
+struct AStarState<B = Arc<Board>> {
     fscore: u32,
-    board: Rc<Board>,
+    hscore: u32,
+    board: B,
+}
+
+impl<B> AStarState<B> {
+    /// Lowest fscore first, and of those, the one furthest along: the most moves made, so the
+    /// fewest left to go.
+    fn cmp_scores(&self, other: &AStarState<B>) -> Ordering {
+        other.fscore.cmp(&self.fscore).then(other.hscore.cmp(&self.hscore))
+    }
 }
 
+/// Ties are broken by the packed bytes, so boards are expanded in the same order, and the same
+/// solution found, however the open set happens to be laid out.
+impl Ord for AStarState<PackedBoard> {
+    fn cmp(&self, other: &AStarState<PackedBoard>) -> Ordering {
+        self.cmp_scores(other).then_with(|| other.board.key().cmp(self.board.key()))
+    }
+}
+
+/// The parallel solver's order depends on which threads get there first anyway, so ties aren't
+/// broken any further.
 impl Ord for AStarState {
     fn cmp(&self, other: &AStarState) -> Ordering {
-        other.fscore.cmp(&self.fscore)
+        self.cmp_scores(other)
     }
 }
 
-impl PartialOrd for AStarState {
-    fn partial_cmp(&self, other: &AStarState) -> Option<Ordering> {
+impl<B: Eq> PartialOrd for AStarState<B> where AStarState<B>: Ord {
+    fn partial_cmp(&self, other: &AStarState<B>) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
 
+/// How many suits of dragons still need to be grouped.
+fn ungrouped_dragon_suits(board: &Board) -> u32 {
+    if board.rules().dragons == 0 {
+        return 0;
+    }
+    let stacks = board.free_cells().iter()
+        .filter(|cell| cell.top().is_some_and(|card| *card == Card::DragonStack))
+        .count();
+    (board.rules().suits - stacks) as u32
+}
+
 /// "hscore". An ~optimistic guess of how many moves it'll take to solve.
 ///
 /// Considers automoves as moves, thus this heuristic is not
@@ -76,51 +100,88 @@ fn estimated_moves_to_solve(board: &Board) -> u32 {
     let ungoaled_numcards: u32 = board.goal_cells().iter().map(|goal_cell|
         match goal_cell.top() {
             Some(rc) => match *rc {
-                Card::NumberCard{rank, ..} => 9 - rank as u32,
+                Card::NumberCard{rank, ..} => (board.rules().ranks - rank) as u32,
                 _ => unreachable!(),  // no other card type should be in a goal cell
             },
-            None => 9,
+            None => board.rules().ranks as u32,
         }
     ).sum();
 
     // Count how many dragon suits still need to be grouped.
-    let ungrouped_dragon_suits: u32 = board.free_cells().iter().map(|cell| match cell.top() {
-        Some(rc) => match *rc {
-            Card::DragonStack => 0,
-            _ => 1,
-        },
-        None => 1,
-    }).sum();
+    let ungrouped_dragon_suits = ungrouped_dragon_suits(board);
 
-    // Count how many dragons are trapped under dragons of the same suit.
-    // These will require a move to separate em before they can be grouped.
-    // If we know all of our dragons are already grouped we skip this
-    // check entirely.
-    let trapped_dragons: u32 = if ungrouped_dragon_suits == 0 {0} else {
-        board.game_cells().iter().map(|game_cell| match &**game_cell {
-            &CardCell::GameCell{ref card_stack} => {
-                let rust_pls: u32 = counter(
-                    card_stack.iter().filter_map(|rc|
-                        match **rc {
-                            Card::DragonCard{suit} => Some(suit),
-                            _ => None,
-                        }
-                    )
-                ).values().map(|num| num - 1).sum();
-                rust_pls
-            },
-            _ => unreachable!(),  // should only be gamecells
-        }).sum()
-    };
+    // If we know all of our dragons are already grouped we skip counting trapped ones entirely.
+    let trapped_dragons = if ungrouped_dragon_suits == 0 {0} else {trapped_dragons(board)};
 
     ungoaled_numcards + trapped_dragons + ungrouped_dragon_suits
 }
 
-fn get_valid_dests(board: &Board) -> Vec<&CardCellIndex> {
+/// How many dragons are trapped under dragons of the same suit. These will require a move to
+/// separate em before they can be grouped.
+pub fn trapped_dragons(board: &Board) -> u32 {
+    board.game_cells().iter().map(|game_cell| match **game_cell {
+        CardCell::GameCell{ref card_stack} => {
+            let rust_pls: u32 = counter(
+                card_stack.iter().filter_map(|rc|
+                    match **rc {
+                        Card::DragonCard{suit} => Some(suit),
+                        _ => None,
+                    }
+                )
+            ).values().map(|num| num - 1).sum();
+            rust_pls
+        },
+        _ => unreachable!(),  // should only be gamecells
+    }).sum()
+}
+
+/// An admissable "hscore": a number of moves it will certainly take, at least, to solve.
+///
+/// Only player moves count; automoves are free. Each count below is of moves that can't be
+/// counted by any other, so their sum never overestimates:
+///
+/// - Each suit of dragons still needs grouping, which is a move of its own.
+/// - Each dragon trapped under a dragon of the same suit needs to be moved out from there, and
+///   dragons only ever move one at a time.
+/// - Each column where a number card sits above a lower number card of the same suit needs at
+///   least one number card moved out of it, since the upper card can't be automoved first.
+fn minimum_moves_to_solve(board: &Board) -> u32 {
+    let ungrouped_dragon_suits = ungrouped_dragon_suits(board);
+
+    let column_moves: u32 = board.game_cells().iter().map(|game_cell| match **game_cell {
+        CardCell::GameCell{ref card_stack} => {
+            let trapped_dragons: u32 = counter(
+                card_stack.iter().filter_map(|rc|
+                    match **rc {
+                        Card::DragonCard{suit} => Some(suit),
+                        _ => None,
+                    }
+                )
+            ).values().map(|num| num - 1).sum();
+
+            let blocked = card_stack.iter().enumerate().any(|(i, upper)| match **upper {
+                Card::NumberCard{suit, rank} => card_stack[..i].iter().any(|lower| match **lower {
+                    Card::NumberCard{suit: lower_suit, rank: lower_rank} =>
+                        lower_suit == suit && lower_rank < rank,
+                    _ => false,
+                }),
+                _ => false,
+            });
+
+            trapped_dragons + blocked as u32
+        },
+        _ => unreachable!(),  // should only be gamecells
+    }).sum();
+
+    ungrouped_dragon_suits + column_moves
+}
+
+fn get_valid_dests(board: &Board, automoves: AutoMovePolicy) -> Vec<CardCellIndex> {
     let mut seen_free_cell = false;
     let mut seen_free_game_cell = false;
+    let mut seen_free_goal_cell = false;
 
-    DEST_SLOTS.iter().filter(|slot| {
+    dest_slots(board.rules()).into_iter().filter(|slot| {
         let top_card = board.get_cell(slot).top();
         match slot {
             // Only consider one empty cell, and don't consider occupied cells.
@@ -153,41 +214,63 @@ fn get_valid_dests(board: &Board) -> Vec<&CardCellIndex> {
                     },
                 }
             },
-            // Never consider an empty cell (because the automove will take care of it)
-            CardCellIndex::GoalCellIndex(_) => top_card.is_some(),
+            // Only consider one empty cell, and only if 1s aren't automoved there anyway.
+            CardCellIndex::GoalCellIndex(_) => {
+                if top_card.is_some() {
+                    true
+                }
+                else if seen_free_goal_cell || board.auto_safe_rank(automoves) >= 1 {
+                    false
+                }
+                else {
+                    seen_free_goal_cell = true;
+                    true
+                }
+            },
         }
     }).collect()
 }
 
 
-pub fn next_states(board: &Board) -> Vec<Board> {
+/// Every move that can be made on `board`, and the board it leads to once `automoves` has made
+/// its moves.
+pub fn next_states(board: &Board, automoves: AutoMovePolicy) -> Vec<(Move, Board)> {
     let mut states = Vec::new();
     // Group dragons
-    for suit in vec![Suit::Black, Suit::Green, Suit::Red] {
+    for &suit in board.rules().suits_in_play() {
         if let Some(new_board) = board.stack_dragons(suit) {
-            states.push(new_board.do_automoves());
+            states.push((Move::GroupDragons{suit}, new_board.do_automoves_with(automoves)));
         }
     }
     // Just try all moves.
     // We can do a little preprocessing on clearly invalid source and dest slots
     // before doing n * m comparisons.
-    let source_slots = SOURCE_SLOTS.iter().filter(|slot| {
+    let source_slots = source_slots(board.rules()).into_iter().filter(|slot| {
         let top_card = board.get_cell(slot).top();
         match top_card {
             None => false,
             Some(card) => if let Card::DragonStack = &*card {false} else {true},
         }
     });
-    let dest_slots = get_valid_dests(board);
+    let dest_slots = get_valid_dests(board, automoves);
 
-    for source_slot in source_slots {
+    for ref source_slot in source_slots {
         for dest_slot in dest_slots.iter() {
             match board.move_stack(source_slot, dest_slot) {
-                Ok(new_board) => states.push(new_board.do_automoves()),
+                Ok(new_board) => {
+                    let height = board.get_cell(source_slot).len() - new_board.get_cell(source_slot).len();
+                    states.push((
+                        Move::MoveStack{source: *source_slot, dest: *dest_slot, height: height as u8},
+                        new_board.do_automoves_with(automoves),
+                    ));
+                },
                 Err(MoveStackError::AmbiguousMove(max_height)) =>
-                    for height in 1..=max_height as usize {
-                        if let Some(new_board) = board.move_n_cards(source_slot, dest_slot, height) {
-                            states.push(new_board.do_automoves());
+                    for height in 1..=max_height {
+                        if let Some(new_board) = board.move_n_cards(source_slot, dest_slot, height as usize) {
+                            states.push((
+                                Move::MoveStack{source: *source_slot, dest: *dest_slot, height},
+                                new_board.do_automoves_with(automoves),
+                            ));
                         }
                     }
                 Err(MoveStackError::InvalidMove) => (),
@@ -197,62 +280,316 @@ pub fn next_states(board: &Board) -> Vec<Board> {
     states
 }
 
-pub fn solve(board: &Board) -> Option<Vec<Board>> {
-    Some(solve_rc(board)?.into_iter().map(|board|
-        Rc::try_unwrap(board).unwrap_or_else(|_| panic!("Didn't drop all the refs :(((("))
-    ).collect())
+/// A winning line of play.
+pub struct Solution {
+    /// Every board along the way, starting with the board that was solved. Each board after the
+    /// first is the result of making the corresponding move and any automoves that follow it.
+    pub boards: Vec<Board>,
+    /// The moves to make, in order. There is always one fewer move than there are boards.
+    pub moves: Vec<Move>,
+}
+
+/// A callback for reporting a search's progress, given its statistics so far.
+pub type Progress = dyn Fn(&SolverStats) + Send + Sync;
+
+/// Limits on how much work a search may do before giving up, and how to report on it while it
+/// runs. The default is no limits at all, and no reporting.
+#[derive(Clone)]
+pub struct SolverConfig {
+    /// Give up after expanding this many boards.
+    pub max_nodes: Option<u64>,
+    /// Give up after searching for this long.
+    pub max_time: Option<Duration>,
+    /// Give up once the boards the search is holding on to take roughly this many bytes.
+    pub max_memory: Option<usize>,
+    /// Called with the statistics so far, every `progress_interval` while the search runs.
+    pub progress: Option<Arc<Progress>>,
+    /// How long to wait between calls to `progress`.
+    pub progress_interval: Duration,
+    /// How many threads `solve` searches with. With more than one, the threads share a single
+    /// search, so the solution found may differ from run to run, but is about as short.
+    pub threads: usize,
+    /// Which moves are made automatically after each move. The board being solved should already
+    /// have had them made.
+    pub automoves: AutoMovePolicy,
+}
+
+impl Default for SolverConfig {
+    fn default() -> SolverConfig {
+        SolverConfig{
+            max_nodes: None,
+            max_time: None,
+            max_memory: None,
+            progress: None,
+            progress_interval: Duration::from_secs(1),
+            threads: 1,
+            automoves: AutoMovePolicy::default(),
+        }
+    }
+}
+
+/// What a search did to get where it got.
+#[derive(Clone, Debug, Default)]
+pub struct SolverStats {
+    /// Boards whose next states were searched.
+    pub nodes_expanded: u64,
+    /// Next states found while expanding boards.
+    pub nodes_generated: u64,
+    /// Next states passed over because they had already been reached in as few moves.
+    pub duplicates_skipped: u64,
+    /// The most boards waiting to be searched at once.
+    pub peak_open: usize,
+    /// The most boards already searched, and remembered as such, at once.
+    pub peak_closed: usize,
+    /// How long the search took, or has taken so far.
+    pub elapsed: Duration,
+    /// The lowest hscore of any board searched, if any were.
+    pub best_hscore: Option<u32>,
+}
+
+impl fmt::Display for SolverStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "nodes expanded:     {}", self.nodes_expanded)?;
+        writeln!(f, "nodes generated:    {}", self.nodes_generated)?;
+        writeln!(f, "duplicates skipped: {}", self.duplicates_skipped)?;
+        writeln!(f, "peak open set:      {}", self.peak_open)?;
+        writeln!(f, "peak closed set:    {}", self.peak_closed)?;
+        match self.best_hscore {
+            Some(hscore) => writeln!(f, "best hscore:        {}", hscore)?,
+            None => writeln!(f, "best hscore:        -")?,
+        }
+        write!(f, "elapsed:            {:.3}s", self.elapsed.as_secs_f64())
+    }
+}
+
+/// Rough number of bytes the search uses per board it holds on to: the board itself, the cells
+/// replaced by the move that reached it, and its entries in the search's tables. The board and its
+/// cells take about 330 bytes, as measured by the `memory` test in `packed`; the table entries are
+/// estimated from the size of what's in them.
+const BYTES_PER_BOARD: usize = 400;
+/// The same, for `solve_rc`, which holds on to `PackedBoard`s instead. These take about 95 bytes,
+/// measured the same way, and the rest is estimated: their table entries are bigger, since the
+/// path to each board is kept in a table of its own.
+const BYTES_PER_PACKED_BOARD: usize = 250;
+
+/// Tracks a single search's statistics, and its use of the limits in a `SolverConfig`.
+struct Budget<'a> {
+    config: &'a SolverConfig,
+    bytes_per_board: usize,
+    start: Instant,
+    last_progress: Instant,
+    stats: SolverStats,
+}
+
+impl<'a> Budget<'a> {
+    fn new(config: &'a SolverConfig) -> Budget<'a> {
+        let start = Instant::now();
+        Budget{config, bytes_per_board: BYTES_PER_BOARD, start, last_progress: start, stats: SolverStats::default()}
+    }
+
+    /// Return true if the search should give up rather than expand another board. Otherwise,
+    /// count the board as expanded and report progress if it's time to.
+    ///
+    /// `open` and `closed` are the sizes of the search's open and closed sets, and
+    /// `stored_boards` is how many boards the search is currently holding on to.
+    fn spend(&mut self, open: usize, closed: usize, stored_boards: usize) -> bool {
+        self.stats.peak_open = self.stats.peak_open.max(open);
+        self.stats.peak_closed = self.stats.peak_closed.max(closed);
+        let now = Instant::now();
+        if self.config.max_nodes.is_some_and(|max| self.stats.nodes_expanded >= max) ||
+                self.config.max_memory.is_some_and(|max| stored_boards * self.bytes_per_board > max) ||
+                self.config.max_time.is_some_and(|max| now.duration_since(self.start) > max) {
+            return true;
+        }
+        self.stats.nodes_expanded += 1;
+        if let Some(ref progress) = self.config.progress {
+            if now.duration_since(self.last_progress) >= self.config.progress_interval {
+                self.last_progress = now;
+                self.stats.elapsed = now.duration_since(self.start);
+                progress(&self.stats);
+            }
+        }
+        false
+    }
+
+    /// Note a board with the given hscore was searched.
+    fn saw_hscore(&mut self, hscore: u32) {
+        if self.stats.best_hscore.is_none_or(|best| hscore < best) {
+            self.stats.best_hscore = Some(hscore);
+        }
+    }
+
+    fn finish(mut self) -> SolverStats {
+        self.stats.elapsed = self.start.elapsed();
+        self.stats
+    }
+}
+
+/// How a search ended.
+pub enum SolveOutcome<P = Solution> {
+    /// A way to win was found.
+    Solved(P),
+    /// Every reachable board was searched, and none of them are solved.
+    ProvenUnsolvable,
+    /// The search ran out of budget before finding a solution. `best_partial` leads to the board
+    /// which looked closest to solved.
+    BudgetExhausted{
+        /// The way to the board that looked closest to solved.
+        best_partial: P,
+    },
+}
+
+impl<P> SolveOutcome<P> {
+    fn map<Q, F>(self, f: F) -> SolveOutcome<Q> where
+        F: FnOnce(P) -> Q,
+    {
+        match self {
+            SolveOutcome::Solved(path) => SolveOutcome::Solved(f(path)),
+            SolveOutcome::ProvenUnsolvable => SolveOutcome::ProvenUnsolvable,
+            SolveOutcome::BudgetExhausted{best_partial} =>
+                SolveOutcome::BudgetExhausted{best_partial: f(best_partial)},
+        }
+    }
+}
+
+/// How a search ended, and what it did along the way.
+pub struct SolveReport<P = Solution> {
+    /// Whether a way to win was found, and if not, why not.
+    pub outcome: SolveOutcome<P>,
+    /// What the search did.
+    pub stats: SolverStats,
+}
+
+/// Why a board wasn't searched at all.
+#[derive(Debug)]
+pub enum SolveError {
+    /// The board could not have come from a real game, so it isn't worth searching.
+    InvalidBoard(Vec<Violation>),
+}
+
+fn check_valid(board: &Board) -> Result<(), SolveError> {
+    let violations = board.validate();
+    if violations.is_empty() {Ok(())}
+    else {Err(SolveError::InvalidBoard(violations))}
+}
+
+/// Find a way to solve the given board, within the limits of `config`.
+///
+/// This is fast, but the solution found may not be the shortest one; see `solve_optimal`.
+/// Boards which fail `Board::validate` are refused, since searching them could take forever.
+pub fn solve(board: &Board, config: &SolverConfig) -> Result<SolveReport, SolveError> {
+    check_valid(board)?;
+    let SolveReport{outcome, stats} = if config.threads > 1 {
+        solve_parallel(board, config)
+    }
+    else {
+        solve_rc(board, config)
+    };
+    Ok(SolveReport{outcome: outcome.map(|path| replay(&path, config.automoves)), stats})
+}
+
+/// Turn a path of boards into a `Solution`.
+///
+/// The boards in `path` are only equal to what each move produces up to the ordering of their
+/// cells, so replay the path to find the moves and the boards they actually produce.
+fn replay(path: &VecDeque<Arc<Board>>, automoves: AutoMovePolicy) -> Solution {
+    let mut boards = vec![(*path[0]).clone()];
+    let mut moves = Vec::new();
+    for target in path.iter().skip(1) {
+        let (mv, next_board) = next_states(boards.last().expect("never empty"), automoves).into_iter()
+            .find(|(_, next_board)| next_board == &**target)
+            .expect("path should only contain reachable boards");
+        moves.push(mv);
+        boards.push(next_board);
+    }
+    Solution{boards, moves}
 }
 
 // A*ly search
-pub fn solve_rc(board: &Board) -> Option<VecDeque<Rc<Board>>> {
-    let board = Rc::new(board.clone());
+//
+// Boards are held on to packed, and only unpacked to be expanded.
+fn solve_rc(board: &Board, config: &SolverConfig) -> SolveReport<VecDeque<Arc<Board>>> {
+    let start = board;
+    let rules = board.rules();
+    // Unpacked boards have their cells in the packed order, but the path needs to start with the
+    // board as given, or the moves found along it would be for the wrong cells.
+    let unpack = |path: VecDeque<PackedBoard>| path.iter().enumerate().map(|(i, board)|
+        Arc::new(if i == 0 {start.clone()} else {board.unpack(rules)})
+    ).collect();
+    let hscore = estimated_moves_to_solve(board);
+    let board = PackedBoard::pack(board);
+    let mut budget = Budget::new(config);
+    budget.bytes_per_board = BYTES_PER_PACKED_BOARD;
     let mut open_set = BinaryHeap::new();
     open_set.push(AStarState{
-        fscore: estimated_moves_to_solve(&*board),
+        fscore: hscore,
+        hscore,
         board: board.clone(),
     });
-    let mut path: HashMap<Rc<Board>, Rc<Board>> = HashMap::new();
-    let mut closed_set = HashSet::new();
-    let mut gscores: HashMap<Rc<Board>, u32> = HashMap::new();  // actual cost of getting here.
+    let mut path: HashMap<PackedBoard, PackedBoard> = HashMap::new();
+    let mut gscores: HashMap<PackedBoard, u32> = HashMap::new();  // actual cost of getting here.
     gscores.insert(board.clone(), 0);  // it "actually" took no moves to start with this board.
+    // The board which looks closest to solved, in case we run out of budget.
+    let mut best = (hscore, board.clone());
 
-    while let Some(AStarState{board, ..}) = open_set.pop() {
+    while let Some(AStarState{board: packed, fscore, hscore}) = open_set.pop() {
+        let gscore = fscore - hscore;
+        // This board has since been reached in fewer moves, and queued again.
+        if gscores[&packed] < gscore {
+            budget.stats.duplicates_skipped += 1;
+            continue;
+        }
+        let board = packed.unpack(rules);
         if board.is_solved() {
-            return Some(reconstruct_path(path, board));
+            return SolveReport{
+                outcome: SolveOutcome::Solved(unpack(reconstruct_path(path, packed))),
+                stats: budget.finish(),
+            };
+        }
+        if hscore < best.0 {
+            best = (hscore, packed.clone());
+        }
+        budget.saw_hscore(hscore);
+        // Every board we know of has a gscore, and the open set may hold copies of them. Those not
+        // waiting in the open set have been expanded; stale copies make this an underestimate.
+        let closed = gscores.len().saturating_sub(open_set.len());
+        if budget.spend(open_set.len(), closed, gscores.len() + open_set.len()) {
+            return SolveReport{
+                outcome: SolveOutcome::BudgetExhausted{best_partial: unpack(reconstruct_path(path, best.1))},
+                stats: budget.finish(),
+            };
         }
-
-        closed_set.insert(board.clone());
 
         // we're trying to minimize moves, and each move is equally
         // costly, so this is a constant `1`.
         // We're also able to hoist this math outta the neighbor loop.
-        let gscore: u32 = gscores.get(&*board).expect("why aint the board in here") + 1;
+        let gscore = gscore + 1;
 
-        for next_board in next_states(&board) {
-            let next_board = Rc::new(next_board);
-            if closed_set.contains(&*next_board) {
+        for (_, next_board) in next_states(&board, config.automoves) {
+            budget.stats.nodes_generated += 1;
+            let next_packed = PackedBoard::pack(&next_board);
+            // The heuristic isn't consistent, so a board may be expanded before the shortest way
+            // to it is found. Once it is, it's queued and expanded again.
+            if gscores.get(&next_packed).is_some_and(|&score| score <= gscore) {
+                budget.stats.duplicates_skipped += 1;
                 continue;
             }
 
-            if let Some(score) = gscores.get(&next_board) {
-                if score < &gscore {
-                    continue;
-                }
-            }
-
-            path.insert(next_board.clone(), board.clone());
-            gscores.insert(next_board.clone(), gscore);
+            path.insert(next_packed.clone(), packed.clone());
+            gscores.insert(next_packed.clone(), gscore);
+            let hscore = estimated_moves_to_solve(&next_board);
             open_set.push(AStarState{
-                fscore: estimated_moves_to_solve(&*next_board) + gscore,
-                board: next_board,  // safe to give on last line of loop
+                fscore: hscore + gscore,
+                hscore,
+                board: next_packed,  // safe to give on last line of loop
             });
         }
     }
-    None
+    SolveReport{outcome: SolveOutcome::ProvenUnsolvable, stats: budget.finish()}
 }
 
-fn reconstruct_path(mut path: HashMap<Rc<Board>, Rc<Board>>, board: Rc<Board>) -> VecDeque<Rc<Board>> {
-    let mut result: VecDeque<Rc<Board>> = VecDeque::new();
+fn reconstruct_path<B: Clone + Eq + Hash>(mut path: HashMap<B, B>, board: B) -> VecDeque<B> {
+    let mut result: VecDeque<B> = VecDeque::new();
     result.push_front(board.clone());
     // Would be great to `while let Some(board) = path.remove(&board)` here,
     // but the `let` rebinds the name `board` to a too-small scope, shadowing
@@ -268,16 +605,435 @@ fn reconstruct_path(mut path: HashMap<Rc<Board>, Rc<Board>>, board: Rc<Board>) -
     result
 }
 
+/// The open set of `solve_parallel`, and what its workers need to know about it.
+struct ParallelOpenSet {
+    heap: BinaryHeap<AStarState>,
+    /// How many workers are expanding boards, and so may add to the heap.
+    busy: usize,
+    /// Set once the workers should stop.
+    done: bool,
+    /// The solved board reached in the fewest moves so far, and how many.
+    solution: Option<(u32, Arc<Board>)>,
+    /// The board which looks closest to solved, in case we run out of budget.
+    best: (u32, Arc<Board>),
+}
+
+/// One shard of `Shared::table`: each board reached, the fewest moves it took, and the board it
+/// was reached from.
+type TableShard = Mutex<HashMap<Arc<Board>, (u32, Option<Arc<Board>>)>>;
+
+/// What the workers of `solve_parallel` share.
+struct Shared<'a> {
+    config: &'a SolverConfig,
+    start: Instant,
+    open_set: Mutex<ParallelOpenSet>,
+    /// Notified whenever the open set gains boards, or the workers should stop.
+    changed: Condvar,
+    /// The fewest moves it's taken to reach each board, and the board it was reached from. Split
+    /// into shards by hash, so workers rarely wait on each other.
+    table: Vec<TableShard>,
+    out_of_budget: AtomicBool,
+    nodes_expanded: AtomicU64,
+    nodes_generated: AtomicU64,
+    duplicates_skipped: AtomicU64,
+    /// Boards in the table.
+    stored: AtomicUsize,
+    peak_open: AtomicUsize,
+    best_hscore: AtomicU32,
+}
+
+const TABLE_SHARDS: usize = 64;
+
+impl<'a> Shared<'a> {
+    fn new(config: &'a SolverConfig, board: Arc<Board>) -> Shared<'a> {
+        let hscore = estimated_moves_to_solve(&board);
+        let mut heap = BinaryHeap::new();
+        heap.push(AStarState{fscore: hscore, hscore, board: board.clone()});
+        let shared = Shared{
+            config,
+            start: Instant::now(),
+            open_set: Mutex::new(ParallelOpenSet{
+                heap,
+                busy: 0,
+                done: false,
+                solution: None,
+                best: (hscore, board.clone()),
+            }),
+            changed: Condvar::new(),
+            table: (0..TABLE_SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+            out_of_budget: AtomicBool::new(false),
+            nodes_expanded: AtomicU64::new(0),
+            nodes_generated: AtomicU64::new(0),
+            duplicates_skipped: AtomicU64::new(0),
+            stored: AtomicUsize::new(0),
+            peak_open: AtomicUsize::new(1),
+            best_hscore: AtomicU32::new(u32::MAX),
+        };
+        shared.reach(&board, 0, None);
+        shared
+    }
+
+    fn shard(&self, board: &Board) -> &TableShard {
+        let mut hasher = DefaultHasher::new();
+        board.hash(&mut hasher);
+        &self.table[hasher.finish() as usize % self.table.len()]
+    }
+
+    /// Record reaching `board` from `parent` in `gscore` moves. Returns false if it had already
+    /// been reached in as few.
+    fn reach(&self, board: &Arc<Board>, gscore: u32, parent: Option<Arc<Board>>) -> bool {
+        let mut shard = self.shard(board).lock().unwrap();
+        match shard.get(&**board) {
+            Some(&(score, _)) if score <= gscore => return false,
+            Some(_) => (),
+            None => {self.stored.fetch_add(1, Relaxed);},
+        }
+        shard.insert(board.clone(), (gscore, parent));
+        true
+    }
+
+    /// Whether `gscore` is still the fewest moves `board` has been reached in.
+    fn is_current(&self, board: &Board, gscore: u32) -> bool {
+        self.shard(board).lock().unwrap().get(board).map(|&(score, _)| score) == Some(gscore)
+    }
+
+    fn path_to(&self, board: Arc<Board>) -> VecDeque<Arc<Board>> {
+        let mut result = VecDeque::new();
+        let mut board = Some(board);
+        while let Some(next) = board {
+            board = self.shard(&next).lock().unwrap().get(&*next).and_then(|(_, parent)| parent.clone());
+            result.push_front(next);
+        }
+        result
+    }
+
+    /// Return true if the search should give up rather than expand another board, and otherwise
+    /// count the board as expanded.
+    ///
+    /// Only called with the open set locked, so no other worker counts a board in the meantime.
+    fn spend(&self, open: usize) -> bool {
+        let nodes = self.nodes_expanded.load(Relaxed);
+        let stored_boards = self.stored.load(Relaxed) + open;
+        let out_of_budget =
+            self.config.max_nodes.is_some_and(|max| nodes >= max) ||
+            self.config.max_memory.is_some_and(|max| stored_boards * BYTES_PER_BOARD > max) ||
+            self.config.max_time.is_some_and(|max| self.start.elapsed() > max);
+        if out_of_budget {
+            self.out_of_budget.store(true, Relaxed);
+        }
+        else {
+            self.nodes_expanded.fetch_add(1, Relaxed);
+        }
+        out_of_budget
+    }
+
+    fn stats(&self) -> SolverStats {
+        let best_hscore = self.best_hscore.load(Relaxed);
+        SolverStats{
+            nodes_expanded: self.nodes_expanded.load(Relaxed),
+            nodes_generated: self.nodes_generated.load(Relaxed),
+            duplicates_skipped: self.duplicates_skipped.load(Relaxed),
+            peak_open: self.peak_open.load(Relaxed),
+            peak_closed: self.stored.load(Relaxed),
+            elapsed: self.start.elapsed(),
+            best_hscore: if best_hscore == u32::MAX {None} else {Some(best_hscore)},
+        }
+    }
+
+    /// Take boards from the open set and expand them, until the search is over.
+    fn work(&self) {
+        let mut open_set = self.open_set.lock().unwrap();
+        while !open_set.done {
+            let AStarState{board, fscore, hscore} = match open_set.heap.pop() {
+                Some(state) => state,
+                // Someone may yet add to the open set.
+                None if open_set.busy > 0 => {
+                    open_set = self.changed.wait(open_set).unwrap();
+                    continue;
+                },
+                None => break,
+            };
+            // Boards come out in order of fscore, and a solved board's fscore is its gscore, so
+            // nothing left can lead to a shorter solution.
+            if open_set.solution.as_ref().is_some_and(|&(moves, _)| fscore >= moves) {
+                break;
+            }
+            let gscore = fscore - hscore;
+            // This board has since been reached in fewer moves, and queued again.
+            if !self.is_current(&board, gscore) {
+                self.duplicates_skipped.fetch_add(1, Relaxed);
+                continue;
+            }
+            if board.is_solved() {
+                // Other workers may still be expanding boards which lead to shorter solutions.
+                if open_set.solution.as_ref().is_none_or(|&(moves, _)| gscore < moves) {
+                    open_set.solution = Some((gscore, board));
+                }
+                continue;
+            }
+            if hscore < open_set.best.0 {
+                open_set.best = (hscore, board.clone());
+            }
+            self.best_hscore.fetch_min(hscore, Relaxed);
+            if self.spend(open_set.heap.len()) {
+                break;
+            }
+
+            open_set.busy += 1;
+            drop(open_set);
+            let mut next = Vec::new();
+            for (_, next_board) in next_states(&board, self.config.automoves) {
+                self.nodes_generated.fetch_add(1, Relaxed);
+                let next_board = Arc::new(next_board);
+                if !self.reach(&next_board, gscore + 1, Some(board.clone())) {
+                    self.duplicates_skipped.fetch_add(1, Relaxed);
+                    continue;
+                }
+                let hscore = estimated_moves_to_solve(&next_board);
+                next.push(AStarState{fscore: hscore + gscore + 1, hscore, board: next_board});
+            }
+            open_set = self.open_set.lock().unwrap();
+            open_set.busy -= 1;
+            open_set.heap.extend(next);
+            self.peak_open.fetch_max(open_set.heap.len(), Relaxed);
+            self.changed.notify_all();
+        }
+        open_set.done = true;
+        self.changed.notify_all();
+    }
+}
+
+/// The least time `solve_parallel` waits on its workers between calls to the progress callback.
+const MIN_PROGRESS_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Like `solve_rc`, but expanding boards on `config.threads` threads at once.
+///
+/// The threads share an open set, and a table of the fewest moves each board has been reached in,
+/// so boards are expanded in the same order as `solve_rc` would, give or take the ones in flight.
+/// Once a solution is found, the search carries on until nothing left in the open set could lead
+/// to a shorter one, so solutions are about as short as `solve_rc`'s.
+fn solve_parallel(board: &Board, config: &SolverConfig) -> SolveReport<VecDeque<Arc<Board>>> {
+    let shared = Shared::new(config, Arc::new(board.clone()));
+
+    thread::scope(|scope| {
+        // Nothing is ever sent; this only notices when every worker is done.
+        let (sender, receiver) = mpsc::channel::<()>();
+        for _ in 0..config.threads.max(1) {
+            let (sender, shared) = (sender.clone(), &shared);
+            scope.spawn(move || {
+                shared.work();
+                drop(sender);
+            });
+        }
+        drop(sender);
+        match config.progress {
+            Some(ref progress) => {
+                // With no wait at all, this thread would spin on `progress` instead of waiting.
+                let interval = config.progress_interval.max(MIN_PROGRESS_INTERVAL);
+                while let Err(mpsc::RecvTimeoutError::Timeout) = receiver.recv_timeout(interval) {
+                    progress(&shared.stats());
+                }
+            },
+            // Only returns once every worker is done.
+            None => {let _ = receiver.recv();},
+        }
+    });
+
+    let (solution, best) = {
+        let open_set = shared.open_set.lock().unwrap();
+        (open_set.solution.clone(), open_set.best.1.clone())
+    };
+    let outcome = match solution {
+        Some((_, board)) => SolveOutcome::Solved(shared.path_to(board)),
+        None if shared.out_of_budget.load(Relaxed) =>
+            SolveOutcome::BudgetExhausted{best_partial: shared.path_to(best)},
+        None => SolveOutcome::ProvenUnsolvable,
+    };
+    SolveReport{outcome, stats: shared.stats()}
+}
+
+/// Find the shortest way to solve the given board, within the limits of `config`.
+///
+/// Uses IDA* with an admissable heuristic, so the solution is guaranteed to have the fewest
+/// possible moves (not counting automoves). This is much slower than `solve`.
+pub fn solve_optimal(board: &Board, config: &SolverConfig) -> Result<SolveReport, SolveError> {
+    check_valid(board)?;
+    let hscore = minimum_moves_to_solve(board);
+    let mut search = IdaStar{
+        boards: vec![board.clone()],
+        moves: Vec::new(),
+        seen: HashMap::new(),
+        bound: hscore,
+        budget: Budget::new(config),
+        best: (hscore, Solution{boards: vec![board.clone()], moves: Vec::new()}),
+    };
+    loop {
+        // Only remember boards seen during this iteration.
+        search.seen.clear();
+        search.seen.insert(board.clone(), 0);
+        let outcome = match search.search() {
+            IdaStarResult::Found =>
+                SolveOutcome::Solved(Solution{boards: search.boards, moves: search.moves}),
+            IdaStarResult::Exceeded(next_bound) => {
+                search.bound = next_bound;
+                continue;
+            },
+            IdaStarResult::Exhausted => SolveOutcome::ProvenUnsolvable,
+            IdaStarResult::OutOfBudget => SolveOutcome::BudgetExhausted{best_partial: search.best.1},
+        };
+        return Ok(SolveReport{outcome, stats: search.budget.finish()});
+    }
+}
+
+enum IdaStarResult {
+    Found,
+    /// No solution within the bound; this is the smallest fscore which exceeded it.
+    Exceeded(u32),
+    /// No solution at all.
+    Exhausted,
+    OutOfBudget,
+}
+
+struct IdaStar<'a> {
+    /// The path being searched, starting with the board being solved.
+    boards: Vec<Board>,
+    moves: Vec<Move>,
+    /// Boards seen during this iteration, and the fewest moves it took to reach them.
+    seen: HashMap<Board, u32>,
+    /// Boards with an fscore above this are not searched during this iteration.
+    bound: u32,
+    budget: Budget<'a>,
+    /// The path to the board which looks closest to solved, in case we run out of budget.
+    best: (u32, Solution),
+}
+
+impl<'a> IdaStar<'a> {
+    /// Depth-first search from the last board in `boards`. On success, `boards` and `moves` hold
+    /// the path to the solution.
+    fn search(&mut self) -> IdaStarResult {
+        let gscore = self.moves.len() as u32;
+        let (hscore, solved) = {
+            let board = self.boards.last().expect("never empty");
+            (minimum_moves_to_solve(board), board.is_solved())
+        };
+        // Even a solved board is out of bounds if it took too many moves to get to, or a longer
+        // solution could be found before a shorter one.
+        if gscore + hscore > self.bound {
+            return IdaStarResult::Exceeded(gscore + hscore);
+        }
+        if solved {
+            return IdaStarResult::Found;
+        }
+        if hscore < self.best.0 {
+            self.best = (hscore, Solution{boards: self.boards.clone(), moves: self.moves.clone()});
+        }
+        self.budget.saw_hscore(hscore);
+        // The path is the open set, and the boards seen this iteration the closed one.
+        if self.budget.spend(self.boards.len(), self.seen.len(), self.seen.len() + self.boards.len()) {
+            return IdaStarResult::OutOfBudget;
+        }
+
+        let mut result = IdaStarResult::Exhausted;
+        for (mv, next_board) in next_states(self.boards.last().expect("never empty"), self.budget.config.automoves) {
+            self.budget.stats.nodes_generated += 1;
+            // If we've already been here in as few moves, everything past here has been searched.
+            if let Some(&score) = self.seen.get(&next_board) {
+                if score <= gscore + 1 {
+                    self.budget.stats.duplicates_skipped += 1;
+                    continue;
+                }
+            }
+            self.seen.insert(next_board.clone(), gscore + 1);
+
+            self.boards.push(next_board);
+            self.moves.push(mv);
+            match self.search() {
+                IdaStarResult::Found => return IdaStarResult::Found,
+                IdaStarResult::OutOfBudget => return IdaStarResult::OutOfBudget,
+                IdaStarResult::Exceeded(next_bound) => result = match result {
+                    IdaStarResult::Exceeded(other_bound) if other_bound < next_bound =>
+                        IdaStarResult::Exceeded(other_bound),
+                    _ => IdaStarResult::Exceeded(next_bound),
+                },
+                IdaStarResult::Exhausted => (),
+            }
+            self.boards.pop();
+            self.moves.pop();
+        }
+        result
+    }
+}
+
+/// A deal the solver found a way to win.
+pub struct SolvableDeal {
+    /// The seed the deal was dealt from.
+    pub seed: Seed,
+    /// The board as dealt, before any automoves.
+    pub board: Board,
+    /// How the solver won it, from `board` once its automoves are made.
+    pub solution: Solution,
+    /// How many deals were tried, including this one.
+    pub deals: usize,
+}
+
+/// Deal random boards by `rules` until `solve` finds a way to win one within the limits of
+/// `config`, trying at most `max_deals` of them if given.
+///
+/// Without any limits in `config`, a deal too hard to solve could be searched forever. The seed
+/// deals the same board again with `Board::deal_with_rules`, or `Board::deal_seeded` for the real
+/// game's rules.
+pub fn deal_solvable(rules: &RuleSet, config: &SolverConfig, max_deals: Option<usize>) -> Option<SolvableDeal> {
+    let mut deals = 0;
+    while max_deals.is_none_or(|max_deals| deals < max_deals) {
+        deals += 1;
+        let seed = Seed::random();
+        let board = Board::deal_with_rules(&seed, rules);
+        let report = solve(&board.do_automoves_with(config.automoves), config)
+            .expect("dealt boards are always valid");
+        if let SolveOutcome::Solved(solution) = report.outcome {
+            return Some(SolvableDeal{seed, board, solution, deals});
+        }
+    }
+    None
+}
+
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
+    use std::sync::Mutex;
+    use ::board::{Seed, Suit};
 
-    #[test]
-    /// Ensure we make the obvious moves when the game is near the end.
-    fn fast_win() {
+    fn expect_solved(result: Result<SolveReport, SolveError>) -> Solution {
+        match result.expect("invalid board").outcome {
+            SolveOutcome::Solved(solution) => solution,
+            _ => panic!("couldn't even solve"),
+        }
+    }
+
+    /// The fewest moves it takes to solve `board`, found by searching every board breadth first.
+    fn bfs_moves(board: &Board, automoves: AutoMovePolicy) -> Option<usize> {
+        let mut seen = HashSet::new();
+        seen.insert(board.clone());
+        let mut boards = vec![board.clone()];
+        let mut moves = 0;
+        while !boards.is_empty() {
+            if boards.iter().any(|board| board.is_solved()) {
+                return Some(moves);
+            }
+            boards = boards.iter().flat_map(|board| next_states(board, automoves))
+                .map(|(_, next)| next)
+                .filter(|next| seen.insert(next.clone()))
+                .collect();
+            moves += 1;
+        }
+        None
+    }
+
+    fn fast_win_board() -> Board {
         // My goodness rust needs named arguments
-        let board = Board::new(
+        Board::new(
             vec![
                 Some(Card::DragonCard{suit: Suit::Green}),
                 Some(Card::DragonStack),
@@ -317,8 +1073,16 @@ mod tests {
                     Card::NumberCard{suit: Suit::Green, rank: 5},
                 ],
             ],
-        );
-        assert_eq!(solve(&board).expect("couldn't even solve").len(), 3);
+        ).unwrap()
+    }
+
+    #[test]
+    /// Ensure we make the obvious moves when the game is near the end.
+    fn fast_win() {
+        let board = fast_win_board();
+        let solution = expect_solved(solve(&board, &SolverConfig::default()));
+        assert_eq!(solution.boards.len(), 3);
+        assert_eq!(solution.moves.len(), 2);
     }
 
     #[test]
@@ -351,8 +1115,257 @@ mod tests {
                 Vec::new(),
                 Vec::new(),
             ],
+        ).unwrap();
+
+        let solution = expect_solved(solve(&board, &SolverConfig::default()));
+        assert_eq!(solution.boards.len(), 3);
+        assert_eq!(
+            solution.moves,
+            vec![
+                Move::MoveStack{
+                    source: CardCellIndex::GameCellIndex(2),
+                    dest: CardCellIndex::GameCellIndex(0),
+                    height: 1,
+                },
+                Move::GroupDragons{suit: Suit::Green},
+            ],
         );
+    }
+
+    #[test]
+    /// Ensure boards that couldn't come from a real game are refused rather than searched.
+    fn refuse_invalid() {
+        let board = Board::new(
+            vec![None, None, None],
+            true,
+            vec![
+                Some(Card::NumberCard{suit: Suit::Red, rank: 9}),
+                Some(Card::NumberCard{suit: Suit::Black, rank: 9}),
+                Some(Card::NumberCard{suit: Suit::Green, rank: 8}),
+            ],
+            vec![Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new()],
+        ).unwrap();
+        match solve(&board, &SolverConfig::default()) {
+            Err(SolveError::InvalidBoard(violations)) => assert_eq!(
+                violations[0],
+                Violation::MissingCard(Card::NumberCard{suit: Suit::Green, rank: 9}),
+            ),
+            _ => panic!("expected the board to be refused"),
+        }
+    }
+
+    #[test]
+    /// Ensure the optimal solver finds the shortest solution, and that its heuristic never
+    /// overestimates along the way.
+    fn optimal_fast_win() {
+        let solution = expect_solved(solve_optimal(&fast_win_board(), &SolverConfig::default()));
+        assert_eq!(solution.moves.len(), 2);
+        for (i, board) in solution.boards.iter().enumerate() {
+            assert!(minimum_moves_to_solve(board) as usize <= solution.moves.len() - i);
+        }
+        let mut board = fast_win_board();
+        for mv in solution.moves.iter() {
+            board = board.apply_move(mv).expect("moves should be legal").do_automoves();
+        }
+        assert!(board.is_solved());
+    }
+
+    #[test]
+    /// Ensure cards the policy doesn't automove are moved to the goal, empty goal cells included.
+    fn automove_policies() {
+        // XXX  J 99-
+        //  R1-R9, with R1 on top
+        let board = Board::new(
+            vec![Some(Card::DragonStack), Some(Card::DragonStack), Some(Card::DragonStack)],
+            true,
+            vec![
+                Some(Card::NumberCard{suit: Suit::Black, rank: 9}),
+                Some(Card::NumberCard{suit: Suit::Green, rank: 9}),
+                None,
+            ],
+            vec![
+                (1..10).rev().map(|rank| Card::NumberCard{suit: Suit::Red, rank}).collect(),
+                Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(),
+            ],
+        ).unwrap();
+        assert!(board.do_automoves().is_solved());
+
+        for &(automoves, moves) in &[(AutoMovePolicy::Conservative, 8), (AutoMovePolicy::None, 9)] {
+            let config = SolverConfig{automoves, ..SolverConfig::default()};
+            let start = board.do_automoves_with(automoves);
+            let solution = expect_solved(solve(&start, &config));
+            assert_eq!(solution.moves.len(), moves);
+            let solution = expect_solved(solve_optimal(&start, &config));
+            assert_eq!(solution.moves.len(), moves);
+        }
+    }
+
+    #[test]
+    /// Ensure the optimal solver doesn't settle for a solution one move past its bound, on a board
+    /// where the heuristic reaches 0 a move before the end.
+    fn optimal_within_bound() {
+        let rules = RuleSet::from_string("suits=2,ranks=5,dragons=2").unwrap();
+        let board = Board::deal_with_rules(
+            &Seed::from_string("cUmOnyOrRqV%m}0Kk*>u6I0>Jdqq]K@7Z>7hfg=}").unwrap(), &rules,
+        ).do_automoves();
+        let solution = expect_solved(solve_optimal(&board, &SolverConfig::default()));
+        assert_eq!(solution.moves.len(), 3);
+    }
+
+    #[test]
+    /// Ensure a board with no legal moves is reported as unsolvable, rather than as giving up.
+    fn proven_unsolvable() {
+        // XXX  J 99-
+        //  2345678
+        //  1      9  <- ...with the green 1 trapped under the green 2
+        let mut columns = vec![vec![
+            Card::NumberCard{suit: Suit::Green, rank: 1},
+            Card::NumberCard{suit: Suit::Green, rank: 2},
+        ]];
+        for rank in 3..10 {
+            columns.push(vec![Card::NumberCard{suit: Suit::Green, rank}]);
+        }
+        let board = Board::new(
+            vec![Some(Card::DragonStack), Some(Card::DragonStack), Some(Card::DragonStack)],
+            true,
+            vec![
+                Some(Card::NumberCard{suit: Suit::Red, rank: 9}),
+                Some(Card::NumberCard{suit: Suit::Black, rank: 9}),
+                None,
+            ],
+            columns,
+        ).unwrap();
+        match solve(&board, &SolverConfig::default()).map(|report| report.outcome) {
+            Ok(SolveOutcome::ProvenUnsolvable) => (),
+            _ => panic!("expected the board to be proven unsolvable"),
+        }
+        match solve_optimal(&board, &SolverConfig::default()).map(|report| report.outcome) {
+            Ok(SolveOutcome::ProvenUnsolvable) => (),
+            _ => panic!("expected the board to be proven unsolvable"),
+        }
+        let config = SolverConfig{threads: 4, ..SolverConfig::default()};
+        match solve(&board, &config).map(|report| report.outcome) {
+            Ok(SolveOutcome::ProvenUnsolvable) => (),
+            _ => panic!("expected the board to be proven unsolvable"),
+        }
+    }
 
-        assert_eq!(solve(&board).expect("couldn't even solve").len(), 3);
+    #[test]
+    /// Ensure searches give up once they've used their budget, returning a partial solution.
+    fn budget_exhausted() {
+        let board = Board::deal_seeded(
+            &Seed::from_string("8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&").unwrap()
+        ).do_automoves();
+        let config = SolverConfig{max_nodes: Some(10), ..SolverConfig::default()};
+        for result in [solve(&board, &config), solve_optimal(&board, &config)] {
+            let report = result.expect("invalid board");
+            assert_eq!(report.stats.nodes_expanded, 10);
+            match report.outcome {
+                SolveOutcome::BudgetExhausted{best_partial} => {
+                    assert!(best_partial.boards[0] == board);
+                    assert_eq!(best_partial.boards.len(), best_partial.moves.len() + 1);
+                },
+                _ => panic!("expected the search to give up"),
+            }
+        }
+    }
+
+    #[test]
+    /// Ensure statistics are collected, and progress is reported while searching.
+    fn stats_and_progress() {
+        let reports = Arc::new(Mutex::new(0));
+        let counter = reports.clone();
+        let config = SolverConfig{
+            progress: Some(Arc::new(move |_: &SolverStats| *counter.lock().unwrap() += 1)),
+            progress_interval: Duration::from_secs(0),
+            ..SolverConfig::default()
+        };
+        let report = solve(&fast_win_board(), &config).expect("invalid board");
+        let stats = report.stats;
+        assert!(stats.nodes_expanded > 0);
+        assert!(stats.nodes_generated >= stats.duplicates_skipped);
+        assert!(stats.peak_open > 0);
+        assert_eq!(stats.best_hscore, Some(1));
+        assert_eq!(*reports.lock().unwrap() as u64, stats.nodes_expanded);
+    }
+
+    #[test]
+    /// Ensure a solvable deal's seed deals the board that was solved.
+    fn solvable_deal() {
+        let rules = RuleSet::from_string("suits=2,ranks=5,dragons=2").unwrap();
+        let config = SolverConfig{max_nodes: Some(10_000), ..SolverConfig::default()};
+        let deal = deal_solvable(&rules, &config, Some(100)).expect("should find a solvable deal");
+        assert!(Board::deal_with_rules(&deal.seed, &rules) == deal.board);
+        assert!(deal.solution.boards[0] == deal.board.do_automoves());
+        assert!(deal.solution.boards.last().expect("never empty").is_solved());
+        assert!(1 <= deal.deals && deal.deals <= 100);
+
+        assert!(deal_solvable(&rules, &config, Some(0)).is_none());
+    }
+
+    #[test]
+    /// Ensure `solve_rc` finds the shortest solution to deals small enough to search exhaustively.
+    /// Without automoves every card needs a move of its own, so the heuristic never overestimates
+    /// and the shortest solution is the one A* should find.
+    fn shortest_solutions() {
+        let rules = RuleSet::from_string("free=2,columns=4,suits=2,ranks=4,dragons=2").unwrap();
+        let config = SolverConfig{automoves: AutoMovePolicy::None, ..SolverConfig::default()};
+        for seed in &[
+            "dx:v?o$%*4+5qE{)s9a3Z<vSYRZJ80NZLGdquT.P",
+            "O4KlZJ9#g7/gygmV)Efe/wZNGB?u<aac-PLVgwpx",
+            "G%7TxEEd[OuzHGGGsUjLRW?cmCglUng=AmJ-/=f#",
+            "WRu4/FNLN85<6PA:KJ#bA$H2w}1YW[*e92nZ?3Fp",
+            "I!ZVm1H*pq>OAU<I{:BYdFJ9&>#}*AK6=9<1SjTI",
+            "9I5D[T?sxqSaB*lW^K&0ws{J.xiR?S}OKjvF{l0d",
+        ] {
+            let board = Board::deal_with_rules(&Seed::from_string(seed).unwrap(), &rules);
+            let moves = match solve_rc(&board, &config).outcome {
+                SolveOutcome::Solved(path) => Some(path.len() - 1),
+                _ => None,
+            };
+            assert!(moves.is_some());
+            assert_eq!(moves, bfs_moves(&board, config.automoves), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    /// Ensure the same board always gets the same solution, however the open set and tables are
+    /// laid out, so solutions can be kept and compared against later.
+    fn deterministic() {
+        let rules = RuleSet::from_string("suits=3,ranks=6,dragons=3").unwrap();
+        let board = Board::deal_with_rules(
+            &Seed::from_string("WRu4/FNLN85<6PA:KJ#bA$H2w}1YW[*e92nZ?3Fp").unwrap(), &rules,
+        ).do_automoves();
+        // Every map in a search hashes with its own random keys, so each run lays them out anew.
+        for _ in 0..3 {
+            let solution = expect_solved(solve(&board, &SolverConfig::default()));
+            let moves: Vec<String> = solution.moves.iter().map(::notation::format_move).collect();
+            assert_eq!(moves.join(", "), concat!(
+                "c6 c8 1, c2 f1 1, c1 c6 1, c1 g1 1, c1 f2 1, group G, c6 c1 2, c6 g1 1, ",
+                "c5 g1 1, group R, c5 c2 1, c3 c5 1, c7 g1 1, group B, c4 c2 1",
+            ));
+        }
+    }
+
+    #[test]
+    /// Ensure the parallel solver solves and gives up just like `solve_rc`.
+    fn parallel() {
+        let config = SolverConfig{threads: 4, ..SolverConfig::default()};
+        let solution = expect_solved(solve(&fast_win_board(), &config));
+        assert_eq!(solution.moves.len(), 2);
+
+        let board = Board::deal_seeded(
+            &Seed::from_string("8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&").unwrap()
+        ).do_automoves();
+        let solution = expect_solved(solve(&board, &config));
+        assert!(solution.boards.last().expect("never empty").is_solved());
+
+        let config = SolverConfig{max_nodes: Some(10), ..config};
+        let report = solve(&board, &config).expect("invalid board");
+        assert_eq!(report.stats.nodes_expanded, 10);
+        match report.outcome {
+            SolveOutcome::BudgetExhausted{best_partial} => assert!(best_partial.boards[0] == board),
+            _ => panic!("expected the search to give up"),
+        }
     }
 }
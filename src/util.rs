@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 pub fn join_vertical(strings: Vec<String>) -> String {
     let mut result = String::new();
     let columns: Vec<Vec<_>> = strings.iter().map(|str| str.split("\n").collect()).collect();
@@ -14,3 +16,8 @@ pub fn join_vertical(strings: Vec<String>) -> String {
 
     return result;
 }
+
+/// A duration as a (fractional) number of seconds.
+pub fn as_seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 / 1e9
+}
@@ -0,0 +1,70 @@
+//! Zobrist-style hashing of boards.
+//!
+//! Every card on a board gets a random-looking key for where it is: in a goal cell, in a free cell,
+//! in the joker cell, or in a game cell on top of some other card (or of nothing). A board's hash
+//! is the sum of its cards' keys. Nothing about a key says which cell the card is in, only what
+//! kind of cell, so boards which differ only in the order of their cells hash the same, just as
+//! they compare equal. And a move only changes the keys of the cards it moves, so boards keep
+//! their hash up to date as cards move rather than working it out from scratch.
+//!
+//! Keys are summed rather than xored so that cards with the same key, like two grouped stacks of
+//! dragons, don't cancel out.
+
+use ::board::{Card, CardCell};
+
+const GAME: u64 = 1;
+const FREE: u64 = 2;
+const GOAL: u64 = 3;
+const JOKER: u64 = 4;
+
+/// splitmix64's finalizer: spreads each bit of `x` over the whole result.
+fn mix(x: u64) -> u64 {
+    let x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    let x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+/// A small number, different for each card.
+fn card_index(card: &Card) -> u64 {
+    match *card {
+        Card::JokerCard => 1,
+        Card::DragonStack => 2,
+        Card::DragonCard{suit} => 16 * (suit as u64 + 1),
+        Card::NumberCard{suit, rank} => 16 * (suit as u64 + 1) + rank as u64,
+    }
+}
+
+fn key(kind: u64, card: &Card, below: Option<&Card>) -> u64 {
+    mix((kind << 16 | card_index(card) << 8 | below.map_or(0, card_index)).wrapping_add(0x9e3779b97f4a7c15))
+}
+
+/// What `cell` adds to a board's hash.
+pub fn cell_key(cell: &CardCell) -> u64 {
+    match *cell {
+        CardCell::JokerCell{has_joker} => if has_joker {key(JOKER, &Card::JokerCard, None)} else {0},
+        CardCell::FreeCell{ref card} => card.as_ref().map_or(0, |card| key(FREE, card, None)),
+        CardCell::GoalCell{ref top_card} => top_card.as_ref().map_or(0, |card| key(GOAL, card, None)),
+        CardCell::GameCell{ref card_stack} => stack_key(card_stack, 0),
+    }
+}
+
+/// What the cards of `stack` from `from` up add to a board's hash.
+fn stack_key<C: AsRef<Card>>(stack: &[C], from: usize) -> u64 {
+    (from..stack.len()).fold(0u64, |sum, i| sum.wrapping_add(
+        key(GAME, stack[i].as_ref(), if i == 0 {None} else {Some(stack[i - 1].as_ref())})
+    ))
+}
+
+/// How a board's hash changes when `old` is replaced by `new`, to be added with wrapping.
+///
+/// Game cells usually only change at the top, so only the cards above what the two stacks have in
+/// common are looked at.
+pub fn cell_delta(old: &CardCell, new: &CardCell) -> u64 {
+    match (old, new) {
+        (CardCell::GameCell{card_stack: old}, CardCell::GameCell{card_stack: new}) => {
+            let common = old.iter().zip(new.iter()).take_while(|&(old, new)| old == new).count();
+            stack_key(new, common).wrapping_sub(stack_key(old, common))
+        },
+        _ => cell_key(new).wrapping_sub(cell_key(old)),
+    }
+}
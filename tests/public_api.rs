@@ -0,0 +1,108 @@
+//! Uses the library only as another crate would, through its public API.
+
+extern crate shenzhen_solitaire_solver;
+
+use shenzhen_solitaire_solver::{
+    deal_solvable, solve, solve_optimal, Board, Card, CardCellIndex, Move, RuleSet, Seed, SolveOutcome,
+    SolverConfig, Solution, Suit,
+};
+use shenzhen_solitaire_solver::notation::{format_board, parse_board, parse_board_with_rules};
+use shenzhen_solitaire_solver::rating::{difficulty_with, Tier};
+
+const SEED: &str = "8G/N]fg0uG@3=0^f0mX{UNh/]Ma]@hohdFQVR}p&";
+
+/// A rule set small enough to solve quickly in a debug build.
+fn small_rules() -> RuleSet {
+    RuleSet::from_string("suits=2,ranks=5,dragons=2").unwrap()
+}
+
+fn expect_solved(board: &Board, outcome: SolveOutcome) -> Solution {
+    match outcome {
+        SolveOutcome::Solved(solution) => {
+            assert!(solution.boards[0] == *board);
+            assert!(solution.boards.last().expect("never empty").is_solved());
+            solution
+        },
+        _ => panic!("expected a solution"),
+    }
+}
+
+#[test]
+/// Ensure a seed deals the same valid board every time, and survives being written down.
+fn deal_from_seed() {
+    let seed = Seed::from_string(SEED).unwrap();
+    assert_eq!(seed.to_string(), SEED);
+    let board = Board::deal_seeded(&seed);
+    assert!(board == Board::deal_seeded(&Seed::from_string(&seed.to_string()).unwrap()));
+    assert!(board.validate().is_empty());
+    assert_eq!(board.game_cells().len(), 8);
+    let cards: usize = board.game_cells().iter().map(|cell| cell.len()).sum();
+    assert_eq!(cards, 40);
+}
+
+#[test]
+fn moves() {
+    let board = Board::deal_seeded(&Seed::from_string(SEED).unwrap()).do_automoves();
+    let mv = Move::MoveStack{
+        source: CardCellIndex::GameCellIndex(7),
+        dest: CardCellIndex::FreeCellIndex(0),
+        height: 1,
+    };
+    let next = board.apply_move(&mv).expect("should be legal");
+    assert_eq!(next.get_cell(&CardCellIndex::FreeCellIndex(0)).top(),
+        board.get_cell(&CardCellIndex::GameCellIndex(7)).top());
+    assert!(next.validate().is_empty());
+
+    // The free cell is taken now.
+    let mv = Move::MoveStack{
+        source: CardCellIndex::GameCellIndex(6),
+        dest: CardCellIndex::FreeCellIndex(0),
+        height: 1,
+    };
+    assert!(next.apply_move(&mv).is_none());
+    assert!(next.apply_move(&Move::GroupDragons{suit: Suit::Red}).is_none());
+}
+
+#[test]
+fn notation() {
+    let board = Board::deal_seeded(&Seed::from_string(SEED).unwrap());
+    assert!(parse_board(&format_board(&board)).expect("should parse") == board);
+
+    let rules = small_rules();
+    let board = Board::deal_with_rules(&Seed::from_string(SEED).unwrap(), &rules);
+    assert!(parse_board_with_rules(&format_board(&board), &rules).expect("should parse") == board);
+}
+
+#[test]
+/// Ensure the moves of a solution really do win, when made one at a time.
+fn solve_and_replay() {
+    let rules = small_rules();
+    let config = SolverConfig{max_nodes: Some(10_000), ..SolverConfig::default()};
+    let deal = deal_solvable(&rules, &config, Some(100)).expect("should find a solvable deal");
+    let board = deal.board.do_automoves();
+
+    let solution = expect_solved(&board, solve(&board, &config).expect("valid board").outcome);
+    let mut replayed = board.clone();
+    for mv in solution.moves.iter() {
+        replayed = replayed.apply_move(mv).expect("should be legal").do_automoves();
+    }
+    assert!(replayed.is_solved());
+
+    let optimal = expect_solved(&board, solve_optimal(&board, &config).expect("valid board").outcome);
+    assert!(optimal.moves.len() <= solution.moves.len());
+
+    let difficulty = difficulty_with(&board, &config).expect("valid board");
+    assert!(difficulty.tier.is_some() && difficulty.tier != Some(Tier::Expert));
+}
+
+#[test]
+fn invalid_board() {
+    let board = Board::new(
+        vec![None, None, None],
+        false,
+        vec![None, None, None],
+        vec![vec![Card::JokerCard], vec![], vec![], vec![], vec![], vec![], vec![], vec![]],
+    ).expect("right number of cells");
+    assert!(!board.validate().is_empty());
+    assert!(solve(&board, &SolverConfig::default()).is_err());
+}